@@ -14,9 +14,20 @@ pub enum Error {
     #[error("core error: {0}")]
     StarviewCore(#[from] starview_core::Error),
 
+    #[error("server error: {0}")]
+    StarviewServer(#[from] starview_server::Error),
+
     #[error("serde JSON error: {0}")]
     SerdeJson(#[from] serde_json::Error),
 
     #[error("error when joining threads: {0}")]
     TokioJoin(#[from] tokio::task::JoinError),
+
+    #[error(
+        "msgpack can not be converted to JSON, map keys must be strings and binary values are not supported: {0}"
+    )]
+    UnsupportedMsgpack(starview_net::Error),
+
+    #[error("no version info was received for asset version '{0}'")]
+    MissingVersionInfo(String),
 }
@@ -1,5 +1,6 @@
 mod color;
 mod error;
+mod output;
 mod progress;
 mod subcommands;
 
@@ -7,7 +8,10 @@ use clap::{Parser, Subcommand};
 
 use crate::{
     color::get_clap_styles,
-    subcommands::{fetch, patch},
+    subcommands::{
+        codec, diff_versions, extract, fetch, list, patch, prune, proxy, serve_api,
+        serve_assets, verify,
+    },
 };
 
 pub use error::Error;
@@ -20,6 +24,33 @@ enum Commands {
 
     /// Download files from the game's server
     Fetch(fetch::FetchArgs),
+
+    /// Extract downloaded assets into a single asset tree
+    Extract(extract::Args),
+
+    /// Search the game's files lists
+    List(list::ListArgs),
+
+    /// Check that downloaded assets match the cached asset paths
+    Verify(verify::Args),
+
+    /// Find or delete downloaded files that are no longer referenced by the asset paths
+    Prune(prune::Args),
+
+    /// Compare the archives and files lists of two fetched asset versions
+    DiffVersions(diff_versions::Args),
+
+    /// Emulate the game's API locally using the fetched asset info
+    ServeApi(serve_api::Args),
+
+    /// Serve downloaded assets under the same paths as the game's CDN
+    ServeAssets(serve_assets::Args),
+
+    /// Decode and encode the game's API payloads
+    Codec(codec::CodecArgs),
+
+    /// Record the game's API traffic, or replay recorded traffic
+    Proxy(proxy::ProxyArgs),
 }
 
 #[derive(Debug, Parser)]
@@ -35,6 +66,15 @@ pub async fn run() -> Result<(), clap::Error> {
     let command_result = match cli.command {
         Commands::Patch(args) => patch::patch(args),
         Commands::Fetch(args) => fetch::fetch(args).await,
+        Commands::Extract(args) => extract::extract(args).await,
+        Commands::List(args) => list::list(args).await,
+        Commands::Verify(args) => verify::verify(args).await,
+        Commands::Prune(args) => prune::prune(args).await,
+        Commands::DiffVersions(args) => diff_versions::diff_versions(args).await,
+        Commands::ServeApi(args) => serve_api::serve_api(args).await,
+        Commands::ServeAssets(args) => serve_assets::serve_assets(args).await,
+        Commands::Codec(args) => codec::codec(args).await,
+        Commands::Proxy(args) => proxy::proxy(args).await,
     };
 
     if let Err(err) = command_result {
@@ -0,0 +1,129 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use starview_net::{
+    crypto::encode_base64_msgpack,
+    models::{ApiResponse, DataHeaders, RESULT_CODE_SUCCESS},
+};
+
+use crate::Error;
+
+/// How a command prints its results
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    /// An aligned table
+    Table,
+    /// Pretty-printed JSON
+    Json,
+}
+
+/// How a command writes game data to a file
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum FileFormat {
+    /// Pretty-printed JSON
+    Json,
+    /// Base64 encoded msgpack, wrapped in the headers of a successful response
+    /// the way the game's server sends it
+    Msgpack,
+}
+
+impl FileFormat {
+    /// Returns the extension of files written in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Json => "json",
+            FileFormat::Msgpack => "msgpack",
+        }
+    }
+
+    /// Encodes `value` in this format
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Error> {
+        match self {
+            FileFormat::Json => Ok(serde_json::to_vec_pretty(value)?),
+            FileFormat::Msgpack => {
+                let servertime = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or(0);
+
+                // the file is not tied to a session, so it has no user
+                Ok(encode_base64_msgpack(&ApiResponse {
+                    data_headers: DataHeaders {
+                        short_udid: 0,
+                        viewer_id: 0,
+                        servertime: servertime.try_into().unwrap_or(u32::MAX),
+                        result_code: RESULT_CODE_SUCCESS,
+                        udid: None,
+                    },
+                    data: value,
+                })?
+                .into_bytes())
+            }
+        }
+    }
+}
+
+/// Prints `rows` as a table with a header row,
+/// padding every column to the width of its widest cell.
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(headers.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starview_net::{
+        crypto::decode_base64_msgpack,
+        models::{AssetPaths, AssetPathsFull, AssetPathsInfo},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let asset_paths = AssetPaths {
+            info: AssetPathsInfo {
+                client_asset_version: "2".into(),
+                target_asset_version: "2".into(),
+                eventual_target_asset_version: "2".into(),
+                is_initial: false,
+                latest_maj_first_version: "1".into(),
+            },
+            full: AssetPathsFull {
+                version: "1".into(),
+                archive: Vec::new(),
+            },
+            diff: Vec::new(),
+            asset_version_hash: "hash".into(),
+            device_type: None,
+        };
+
+        let encoded = FileFormat::Msgpack.encode(&asset_paths).unwrap();
+        let response: ApiResponse<AssetPaths> =
+            decode_base64_msgpack(std::str::from_utf8(&encoded).unwrap()).unwrap();
+        assert_eq!(response.data_headers.result_code, RESULT_CODE_SUCCESS);
+        assert_eq!(response.data.info.client_asset_version, "2");
+        assert_eq!(response.data.asset_version_hash, "hash");
+    }
+}
@@ -0,0 +1,38 @@
+use clap::Parser;
+use starview_net::crypto::get_request_checksum;
+
+use crate::Error;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The udid that the request is sent with
+    #[arg(long)]
+    udid: String,
+
+    /// The viewer ID that the request is sent with,
+    /// empty for requests sent before signing up
+    #[arg(long, default_value = "")]
+    viewer_id: String,
+
+    /// The path of the request's URL, such as "/api/index.php/load"
+    #[arg(long)]
+    path: String,
+
+    /// Path to a file containing the request's base64 msgpack body,
+    /// read from stdin if this is not set or is "-"
+    body: Option<String>,
+}
+
+/// Returns the checksum of a request's body, ignoring whitespace around it
+pub(super) fn body_checksum(udid: &str, viewer_id: &str, path: &str, body: &str) -> String {
+    get_request_checksum(udid, viewer_id, path, body.trim())
+}
+
+pub async fn checksum(args: Args) -> Result<(), Error> {
+    let body = super::read_input(args.body.as_deref()).await?;
+    println!(
+        "{}",
+        body_checksum(&args.udid, &args.viewer_id, &args.path, &body)
+    );
+    Ok(())
+}
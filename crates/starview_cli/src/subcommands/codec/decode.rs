@@ -0,0 +1,39 @@
+use clap::Parser;
+use serde::de::IgnoredAny;
+use starview_net::crypto::decode_base64_msgpack;
+
+use crate::Error;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Where to write the JSON,
+    /// printed if this is not set
+    #[arg(long, short)]
+    out_path: Option<String>,
+
+    /// Path to a file containing a base64 msgpack body,
+    /// read from stdin if this is not set or is "-"
+    input: Option<String>,
+}
+
+/// Converts a base64 msgpack body into pretty-printed JSON.
+///
+/// JSON can not represent every msgpack value,
+/// so bodies with non-string map keys or binary values are rejected.
+pub(super) fn decode_body(body: &str) -> Result<String, Error> {
+    let body = body.trim();
+    let value: serde_json::Value = match decode_base64_msgpack(body) {
+        Ok(value) => value,
+        // valid msgpack that failed to convert is valid msgpack that JSON can not represent
+        Err(err) if decode_base64_msgpack::<IgnoredAny>(body).is_ok() => {
+            return Err(Error::UnsupportedMsgpack(err));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+pub async fn decode(args: Args) -> Result<(), Error> {
+    let input = super::read_input(args.input.as_deref()).await?;
+    super::write_output(&decode_body(&input)?, args.out_path.as_deref()).await
+}
@@ -0,0 +1,27 @@
+use clap::Parser;
+use starview_net::crypto::encode_base64_msgpack;
+
+use crate::Error;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Where to write the base64 msgpack body,
+    /// printed if this is not set
+    #[arg(long, short)]
+    out_path: Option<String>,
+
+    /// Path to a file containing JSON,
+    /// read from stdin if this is not set or is "-"
+    input: Option<String>,
+}
+
+/// Converts JSON into a base64 msgpack body
+pub(super) fn encode_body(json: &str) -> Result<String, Error> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    Ok(encode_base64_msgpack(&value)?)
+}
+
+pub async fn encode(args: Args) -> Result<(), Error> {
+    let input = super::read_input(args.input.as_deref()).await?;
+    super::write_output(&encode_body(&input)?, args.out_path.as_deref()).await
+}
@@ -0,0 +1,106 @@
+mod checksum;
+mod decode;
+mod encode;
+
+use clap::{Args, Subcommand};
+use starview_common::fs::write_file;
+use tokio::io::AsyncReadExt;
+
+use crate::Error;
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Convert a base64 msgpack body into pretty-printed JSON.
+    ///
+    /// Bodies with non-string map keys or binary values are rejected,
+    /// since JSON can not represent them
+    Decode(decode::Args),
+    /// Convert JSON into a base64 msgpack body.
+    ///
+    /// JSON arrays are always encoded as msgpack arrays, never as binary values
+    Encode(encode::Args),
+    /// Compute the checksum that the game server expects in a request's `param` header
+    Checksum(checksum::Args),
+}
+
+#[derive(Debug, Args)]
+pub struct CodecArgs {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+pub async fn codec(args: CodecArgs) -> Result<(), Error> {
+    match args.command {
+        Commands::Decode(args) => decode::decode(args).await,
+        Commands::Encode(args) => encode::encode(args).await,
+        Commands::Checksum(args) => checksum::checksum(args).await,
+    }
+}
+
+/// Reads the file at `path`, or stdin if `path` is None or "-"
+async fn read_input(path: Option<&str>) -> Result<String, Error> {
+    match path {
+        Some(path) if path != "-" => Ok(tokio::fs::read_to_string(path).await?),
+        _ => {
+            let mut input = String::new();
+            tokio::io::stdin().read_to_string(&mut input).await?;
+            Ok(input)
+        }
+    }
+}
+
+/// Writes `output` to the file at `path`, or prints it if `path` is None
+async fn write_output(output: &str, path: Option<&str>) -> Result<(), Error> {
+    match path {
+        Some(path) => Ok(write_file(output.as_bytes(), path).await?),
+        None => {
+            println!("{output}");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_encode_round_trip() {
+        let json = r#"{"asset_version":"1.0.1","viewer_id":123,"flags":[true,null]}"#;
+        let body = encode::encode_body(json).unwrap();
+        let decoded: serde_json::Value =
+            serde_json::from_str(&decode::decode_body(&format!("{body}\n")).unwrap()).unwrap();
+        assert_eq!(
+            decoded,
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decode_unsupported() {
+        // {1: "a"} and {"a": <binary 01 02>}
+        for body in ["gQGhYQ==", "gaFhxAIBAg=="] {
+            assert!(matches!(
+                decode::decode_body(body),
+                Err(Error::UnsupportedMsgpack(_))
+            ));
+        }
+        assert!(matches!(
+            decode::decode_body("gQ=="),
+            Err(Error::StarviewNet(_))
+        ));
+    }
+
+    #[test]
+    fn test_body_checksum() {
+        assert_eq!(
+            checksum::body_checksum(
+                "EA5D7426-42A6-474B-26B7-624F5F9B3AF102B3",
+                "",
+                "/api/index.php/tool/signup",
+                "A=\n"
+            ),
+            "4749e61694c31600ad5e564bf22b8e3c68d8d26d"
+        );
+    }
+}
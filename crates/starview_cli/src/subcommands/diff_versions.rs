@@ -0,0 +1,118 @@
+use clap::Parser;
+use starview_core::{
+    cache::{DEFAULT_CACHE_PATH, models::FetchCache},
+    changelog::{Changelog, Changes},
+};
+
+use crate::{Error, color, output::OutputFormat};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the directory where files lists were downloaded,
+    /// only archives are compared if this is not set
+    #[arg(long)]
+    lists_path: Option<String>,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// How the changelog is printed
+    #[arg(long, short, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// The asset version to compare from
+    old_version: String,
+
+    /// The asset version to compare to
+    new_version: String,
+}
+
+/// Prints every added, removed, and changed item in `changes` on its own line,
+/// using `describe` to format each item
+fn print_changes<T>(title: &str, changes: &Changes<T>, describe: impl Fn(&T) -> String) {
+    println!(
+        "{}{}: {} added, {} removed, {} changed{}",
+        color::TEXT_VARIANT.render_fg(),
+        title,
+        changes.added.len(),
+        changes.removed.len(),
+        changes.changed.len(),
+        color::TEXT.render_fg()
+    );
+    for item in &changes.added {
+        println!(
+            "{}+{} {}",
+            color::SUCCESS.render_fg(),
+            color::TEXT.render_fg(),
+            describe(item)
+        );
+    }
+    for item in &changes.removed {
+        println!(
+            "{}-{} {}",
+            color::ERROR.render_fg(),
+            color::TEXT.render_fg(),
+            describe(item)
+        );
+    }
+    for change in &changes.changed {
+        println!(
+            "{}~{} {} -> {}",
+            color::TEXT_VARIANT.render_fg(),
+            color::TEXT.render_fg(),
+            describe(&change.old),
+            describe(&change.new)
+        );
+    }
+}
+
+/// Prints `changelog` as a summary line and a list of changes for archives and files
+fn print_changelog(changelog: &Changelog) {
+    println!(
+        "Changes from '{}' to '{}':",
+        changelog.old_version, changelog.new_version
+    );
+    print_changes("Archives", &changelog.archives, |archive| {
+        format!(
+            "{} ({}, {})",
+            archive.location,
+            indicatif::HumanBytes(archive.size),
+            archive.sha256
+        )
+    });
+    if let Some(files) = &changelog.files {
+        print_changes("Files", files, |entry| {
+            let device = entry.device_type.map(|device| device.name()).unwrap_or("-");
+            format!(
+                "{} [{}] ({}, {})",
+                entry.path,
+                device,
+                indicatif::HumanBytes(entry.size),
+                entry.hash
+            )
+        });
+    }
+}
+
+pub async fn diff_versions(args: Args) -> Result<(), Error> {
+    let cache = FetchCache::from_path(args.cache_path.as_deref().unwrap_or(DEFAULT_CACHE_PATH))
+        .await
+        .map_err(starview_core::Error::from)?;
+
+    let changelog = Changelog::between(
+        &cache,
+        &args.old_version,
+        &args.new_version,
+        args.lists_path.as_deref().map(std::path::Path::new),
+    )
+    .await?;
+
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&changelog)?),
+        OutputFormat::Table => print_changelog(&changelog),
+    }
+
+    Ok(())
+}
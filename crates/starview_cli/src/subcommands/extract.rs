@@ -0,0 +1,103 @@
+use clap::Parser;
+use starview_core::{
+    cache::{DEFAULT_CACHE_PATH, models::FetchCache},
+    extract::{Extractor, state::ExtractState},
+};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    Error, color,
+    progress::{FinishAndClear, ProgressBar},
+};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// If status messages should be displayed
+    #[arg(long, short, default_value_t = false)]
+    quiet: bool,
+
+    /// The version of the assets to extract,
+    /// uses the version that the assets were last upgraded to by default
+    #[arg(long)]
+    asset_version: Option<String>,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// Path to the directory where assets were downloaded
+    assets_path: String,
+
+    /// Path to the directory where the asset tree will be extracted,
+    /// each device is extracted into a directory named after it if both were fetched
+    out_path: String,
+}
+
+/// Receives ExtractState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
+/// printing status to the console.
+async fn watch_extract_state(mut recv: mpsc::UnboundedReceiver<ExtractState>) {
+    let mut progress_bar: Option<indicatif::ProgressBar> = None;
+
+    while let Some(extract_state) = recv.recv().await {
+        match extract_state {
+            ExtractState::ExtractStart(archives) => {
+                println!(
+                    "{}[1/1] {}Extracting archives...",
+                    color::TEXT_VARIANT.render_fg(),
+                    color::TEXT.render_fg()
+                );
+                progress_bar = Some(ProgressBar::progress(archives.try_into().unwrap_or(0)));
+            }
+            ExtractState::ArchiveExtract { .. } => {
+                if let Some(progress) = &progress_bar {
+                    progress.inc(1);
+                }
+            }
+            ExtractState::Finish => {
+                progress_bar.finish_and_clear();
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub async fn extract(args: Args) -> Result<(), Error> {
+    let extract_start_instant = Instant::now();
+    let cache_path = args.cache_path.as_deref().unwrap_or(DEFAULT_CACHE_PATH);
+    let cache = FetchCache::from_path(cache_path)
+        .await
+        .map_err(starview_core::Error::from)?;
+
+    let asset_version = args
+        .asset_version
+        .as_deref()
+        .or(cache.default_asset_version())
+        .ok_or(starview_core::Error::NoAssetVersion)?;
+    let (extractor, state_recv) =
+        Extractor::for_version(&cache, asset_version, &args.assets_path, &args.out_path)?;
+
+    let state_watcher = if args.quiet {
+        None
+    } else {
+        Some(tokio::spawn(watch_extract_state(state_recv)))
+    };
+
+    let extracted_files = extractor.extract().await?;
+
+    if let Some(watcher) = state_watcher {
+        watcher.await?;
+        println!(
+            "{}Successfully extracted {} files of version {} to '{}' in {:?}.{}",
+            color::SUCCESS.render_fg(),
+            extracted_files,
+            asset_version,
+            args.out_path,
+            Instant::now().duration_since(extract_start_instant),
+            color::TEXT.render_fg()
+        )
+    }
+
+    Ok(())
+}
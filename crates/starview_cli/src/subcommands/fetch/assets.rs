@@ -1,13 +1,15 @@
 use clap::Parser;
-use starview_common::enums::DeviceType;
+use indicatif::HumanBytes;
+use starview_common::enums::{DeviceType, MirrorLayout};
 use starview_core::{
     download::state::DownloadState,
     fetch::{
-        FetchConfig, Fetcher,
+        DownloadPlan, FetchConfig, Fetcher,
         state::{DownloadAssetsState, FetchState},
     },
 };
-use tokio::{sync::watch, time::Instant};
+use tokio::{sync::mpsc, time::Instant};
+use tokio_util::sync::CancellationToken;
 
 use crate::{
     Error, color,
@@ -21,19 +23,33 @@ pub struct Args {
     quiet: bool,
 
     /// The version of the assets,
-    /// uses the latset version by default
+    /// uses the latest version by default
     #[arg(long)]
     asset_version: Option<String>,
 
+    /// The asset version that the assets in out_path were last upgraded to,
+    /// uses the version from the last complete download by default.
+    /// Only the diffs from this version to the target version are downloaded
+    #[arg(long)]
+    from_version: Option<String>,
+
     /// The device type that assets will be acquired for
     #[arg(long, short, value_enum, default_value_t = DeviceType::All)]
     device: DeviceType,
 
+    /// How archives are arranged in the asset directory,
+    /// uses the layout of the previous download by default
+    #[arg(long, value_enum)]
+    layout: Option<MirrorLayout>,
+
     /// Path to the starview cache,
     /// "starview.cache" by default
     #[arg(long)]
     cache_path: Option<String>,
 
+    #[command(flatten)]
+    download: super::DownloadArgs,
+
     /// The maximum number of files to download at once
     #[arg(long, short, default_value_t = 5)]
     concurrency: usize,
@@ -42,13 +58,12 @@ pub struct Args {
     out_path: String,
 }
 
-/// Watches a FetchState [`tokio::sync::watch::Receiver`] for any updates,
+/// Receives FetchState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
 /// printing status to the console.
-async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
+async fn watch_fetch_state(mut recv: mpsc::UnboundedReceiver<FetchState>) {
     let mut progress_bar: Option<indicatif::ProgressBar> = None;
 
-    while recv.changed().await.is_ok() {
-        let fetch_state = *recv.borrow_and_update();
+    while let Some(fetch_state) = recv.recv().await {
         if let FetchState::DownloadAssets(state) = fetch_state {
             match state {
                 DownloadAssetsState::FetchAssetInfo => {
@@ -58,6 +73,7 @@ async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
                         color::TEXT.render_fg()
                     );
                 }
+                DownloadAssetsState::Plan(plan) => print_download_plan(&plan),
                 DownloadAssetsState::DownloadStart(total_bytes) => {
                     println!(
                         "{}[2/2] {}Downloading assets...",
@@ -67,10 +83,8 @@ async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
                     progress_bar = Some(ProgressBar::download(total_bytes));
                 }
                 DownloadAssetsState::Download(download_state) => {
-                    if let DownloadState::FileDownload(file_size) = download_state {
-                        if let Some(progress) = &progress_bar {
-                            progress.inc(file_size);
-                        }
+                    if let Some(progress) = &progress_bar {
+                        print_download_state(progress, download_state);
                     }
                 }
                 DownloadAssetsState::Finish => {
@@ -82,9 +96,52 @@ async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
     }
 }
 
+/// Prints which archives will be downloaded to reach the plan's target version
+pub(super) fn print_download_plan(plan: &DownloadPlan) {
+    let source = match &plan.from_version {
+        Some(from_version) => format!("version {from_version}"),
+        None => "the full archives".into(),
+    };
+    println!(
+        "Upgrading from {} to version {} with {} diffs, {} to download ({} saved).",
+        source,
+        plan.target_version,
+        plan.diff_versions.len(),
+        HumanBytes(plan.total_bytes),
+        HumanBytes(plan.saved_bytes)
+    );
+}
+
+/// Updates `progress` with a download state update,
+/// printing errors above the progress bar.
+pub(super) fn print_download_state(
+    progress: &indicatif::ProgressBar,
+    download_state: DownloadState,
+) {
+    match download_state {
+        DownloadState::FileProgress { bytes, .. } => progress.inc(bytes),
+        DownloadState::FileAttemptFailed {
+            discarded_bytes, ..
+        } => progress.dec(discarded_bytes),
+        DownloadState::DownloadError { url, error, .. } => progress.println(format!(
+            "{}Failed to download '{}': {}{}",
+            color::ERROR.render_fg(),
+            url,
+            error,
+            color::TEXT.render_fg()
+        )),
+        _ => {}
+    }
+}
+
 pub async fn fetch_assets(args: Args) -> Result<(), Error> {
     let fetch_start_instant = Instant::now();
-    let config = FetchConfig::new(args.cache_path, Some(args.device), None);
+    let cancellation_token = CancellationToken::new();
+    let config = args.download.apply(
+        FetchConfig::new(args.cache_path.clone(), Some(args.device), None)
+            .layout(args.layout)
+            .cancellation_token(cancellation_token.clone()),
+    );
     let (mut fetcher, state_recv) = Fetcher::new(config).await?;
 
     let state_watcher = if args.quiet {
@@ -93,20 +150,37 @@ pub async fn fetch_assets(args: Args) -> Result<(), Error> {
         Some(tokio::spawn(watch_fetch_state(state_recv)))
     };
 
-    fetcher
-        .download_assets(&args.out_path, args.concurrency)
+    super::cancel_on_ctrl_c(cancellation_token);
+    let summary = fetcher
+        .download_assets(
+            args.asset_version.as_deref(),
+            args.from_version.as_deref(),
+            &args.out_path,
+            args.concurrency,
+        )
         .await?;
 
     if let Some(watcher) = state_watcher {
         watcher.await?;
-        println!(
-            "{}Successfully downloaded assets to '{}' in {:?}.{}",
-            color::SUCCESS.render_fg(),
-            args.out_path,
-            Instant::now().duration_since(fetch_start_instant),
-            color::TEXT.render_fg()
-        )
+        if summary.failed.is_empty() && !summary.cancelled {
+            println!(
+                "{}Successfully downloaded assets to '{}' in {:?}.{}",
+                color::SUCCESS.render_fg(),
+                args.out_path,
+                Instant::now().duration_since(fetch_start_instant),
+                color::TEXT.render_fg()
+            )
+        }
     }
+    super::print_cancelled(&summary);
+    super::print_failed_downloads(
+        &summary,
+        &super::retry_failed_command(
+            &args.out_path,
+            args.cache_path.as_deref(),
+            Some(args.device),
+        ),
+    );
 
     Ok(())
 }
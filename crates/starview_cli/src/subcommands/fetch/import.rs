@@ -0,0 +1,157 @@
+use clap::Parser;
+use starview_common::enums::{DeviceType, MirrorLayout};
+use starview_core::{
+    fetch::{
+        FetchConfig, Fetcher,
+        state::{FetchState, ImportAssetsState},
+    },
+    mirror::state::ImportState,
+};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    Error, color,
+    progress::{FinishAndClear, ProgressBar},
+};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// If status messages should be displayed
+    #[arg(long, short, default_value_t = false)]
+    quiet: bool,
+
+    /// The version of the assets,
+    /// uses the latest version by default
+    #[arg(long)]
+    asset_version: Option<String>,
+
+    /// The device type that assets were acquired for
+    #[arg(long, short, value_enum, default_value_t = DeviceType::All)]
+    device: DeviceType,
+
+    /// How archives are arranged in the asset directory,
+    /// uses the layout of the previous download by default
+    #[arg(long, value_enum)]
+    layout: Option<MirrorLayout>,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// The maximum number of files to hash at once
+    #[arg(long, short, default_value_t = 4)]
+    concurrency: usize,
+
+    /// If files in the way of an imported asset should be moved aside with a ".bak" extension,
+    /// they are reported and left in place by default
+    #[arg(long, default_value_t = false)]
+    force: bool,
+
+    /// Path to the directory containing previously downloaded assets
+    assets_path: String,
+}
+
+/// Receives FetchState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
+/// printing status to the console.
+async fn watch_fetch_state(mut recv: mpsc::UnboundedReceiver<FetchState>) {
+    let mut progress_bar: Option<indicatif::ProgressBar> = None;
+
+    while let Some(fetch_state) = recv.recv().await {
+        if let FetchState::ImportAssets(state) = fetch_state {
+            match state {
+                ImportAssetsState::FetchAssetInfo => {
+                    println!(
+                        "{}[1/2] {}Getting asset information...",
+                        color::TEXT_VARIANT.render_fg(),
+                        color::TEXT.render_fg()
+                    );
+                }
+                ImportAssetsState::Import(ImportState::ImportStart(total_bytes)) => {
+                    println!(
+                        "{}[2/2] {}Hashing existing assets...",
+                        color::TEXT_VARIANT.render_fg(),
+                        color::TEXT.render_fg()
+                    );
+                    progress_bar = Some(ProgressBar::download(total_bytes));
+                }
+                ImportAssetsState::Import(ImportState::FileHash { size, .. }) => {
+                    if let Some(progress) = &progress_bar {
+                        progress.inc(size);
+                    }
+                }
+                ImportAssetsState::Finish => {
+                    progress_bar.finish_and_clear();
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub async fn import_assets(args: Args) -> Result<(), Error> {
+    let import_start_instant = Instant::now();
+    let config = FetchConfig::new(args.cache_path, Some(args.device), None).layout(args.layout);
+    let (mut fetcher, state_recv) = Fetcher::new(config).await?;
+
+    let state_watcher = if args.quiet {
+        None
+    } else {
+        Some(tokio::spawn(watch_fetch_state(state_recv)))
+    };
+
+    let report = fetcher
+        .import_assets(
+            args.asset_version.as_deref(),
+            &args.assets_path,
+            args.concurrency,
+            args.force,
+        )
+        .await?;
+
+    if let Some(watcher) = state_watcher {
+        watcher.await?;
+        println!(
+            "{}Imported {} assets ({} linked into place) from '{}' after hashing {} files in {:?}.{}",
+            color::SUCCESS.render_fg(),
+            report.imported.len(),
+            report.relocated,
+            args.assets_path,
+            report.hashed_files,
+            Instant::now().duration_since(import_start_instant),
+            color::TEXT.render_fg()
+        )
+    }
+
+    for backup in &report.backups {
+        println!(
+            "Moved '{}' out of the way to '{}'.",
+            backup.path.display(),
+            backup.backup_path.display()
+        );
+    }
+
+    if !report.conflicts.is_empty() {
+        println!(
+            "{}Skipped {} assets because other files are in the way:{}",
+            color::ERROR.render_fg(),
+            report.conflicts.len(),
+            color::TEXT.render_fg()
+        );
+        for conflict in &report.conflicts {
+            println!(
+                "  {}: {}",
+                conflict.archive.location,
+                conflict.path.display()
+            );
+        }
+        println!(
+            "Run again with {}--force{} to move them aside.",
+            color::TEXT_VARIANT.render_fg(),
+            color::TEXT.render_fg()
+        );
+    }
+
+    Ok(())
+}
@@ -7,7 +7,7 @@ use starview_core::{
         state::{DownloadFilesListState, FetchState},
     },
 };
-use tokio::{sync::watch, time::Instant};
+use tokio::{sync::mpsc, time::Instant};
 
 use crate::{
     Error, color,
@@ -21,7 +21,7 @@ pub struct Args {
     quiet: bool,
 
     /// The version of the assets,
-    /// uses the latset version by default
+    /// uses the latest version by default
     #[arg(long)]
     asset_version: Option<String>,
 
@@ -34,17 +34,19 @@ pub struct Args {
     #[arg(long, short, value_enum)]
     cache_path: Option<String>,
 
+    #[command(flatten)]
+    download: super::DownloadArgs,
+
     /// Path to the directory where lists will be downloaded
     out_path: String,
 }
 
-/// Watches a FetchState [`tokio::sync::watch::Receiver`] for any updates,
+/// Receives FetchState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
 /// printing status to the console.
-async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
+async fn watch_fetch_state(mut recv: mpsc::UnboundedReceiver<FetchState>) {
     let mut progress_bar: Option<indicatif::ProgressBar> = None;
 
-    while recv.changed().await.is_ok() {
-        let fetch_state = *recv.borrow_and_update();
+    while let Some(fetch_state) = recv.recv().await {
         if let FetchState::DownloadFilesList(state) = fetch_state {
             match state {
                 DownloadFilesListState::FetchAssetInfo => {
@@ -63,9 +65,19 @@ async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
                     progress_bar = Some(ProgressBar::progress(file_count));
                 }
                 DownloadFilesListState::Download(download_state) => {
-                    if let DownloadState::FileDownload(file_size) = download_state {
-                        if let Some(progress) = &progress_bar {
-                            progress.inc(file_size);
+                    if let Some(progress) = &progress_bar {
+                        match download_state {
+                            DownloadState::FileDownload { .. } => progress.inc(1),
+                            DownloadState::DownloadError { url, error, .. } => {
+                                progress.println(format!(
+                                    "{}Failed to download '{}': {}{}",
+                                    color::ERROR.render_fg(),
+                                    url,
+                                    error,
+                                    color::TEXT.render_fg()
+                                ))
+                            }
+                            _ => {}
                         }
                     }
                 }
@@ -80,7 +92,9 @@ async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
 
 pub async fn fetch_files_list(args: Args) -> Result<(), Error> {
     let fetch_start_instant = Instant::now();
-    let config = FetchConfig::new(args.cache_path, Some(args.device), None);
+    let config = args
+        .download
+        .apply(FetchConfig::new(args.cache_path, Some(args.device), None));
     let (mut fetcher, recv) = Fetcher::new(config).await?;
 
     let state_watcher = if args.quiet {
@@ -89,12 +103,14 @@ pub async fn fetch_files_list(args: Args) -> Result<(), Error> {
         Some(tokio::spawn(watch_fetch_state(recv)))
     };
 
-    fetcher.download_files_list(&args.out_path).await?;
+    fetcher
+        .download_files_list(args.asset_version.as_deref(), &args.out_path)
+        .await?;
 
     if let Some(watcher) = state_watcher {
         watcher.await?;
         println!(
-            "{}Successfully files lists to '{}' in {:?}.{}",
+            "{}Successfully downloaded files lists to '{}' in {:?}.{}",
             color::SUCCESS.render_fg(),
             args.out_path,
             Instant::now().duration_since(fetch_start_instant),
@@ -1,10 +1,18 @@
 mod assets;
+mod import;
 mod list;
 mod path;
+mod retry;
+mod watch;
+
+use std::time::Duration;
 
 use clap::{Args, Subcommand};
+use starview_common::enums::DeviceType;
+use starview_core::fetch::{DownloadSummary, FetchConfig};
+use tokio_util::sync::CancellationToken;
 
-use crate::Error;
+use crate::{Error, color};
 
 #[derive(Debug, Subcommand)]
 enum Commands {
@@ -14,6 +22,12 @@ enum Commands {
     Assets(assets::Args),
     /// Fetches files lists
     List(list::Args),
+    /// Retries assets that failed to download during a previous fetch
+    RetryFailed(retry::Args),
+    /// Records assets that already exist in a directory in the cache without downloading them
+    Import(import::Args),
+    /// Polls for new asset versions, downloading each one as it is released
+    Watch(watch::Args),
 }
 
 #[derive(Debug, Args)]
@@ -22,10 +36,191 @@ pub struct FetchArgs {
     command: Commands,
 }
 
+/// Options shared by every subcommand that downloads files
+#[derive(Debug, Args)]
+struct DownloadArgs {
+    /// The maximum download speed in bytes per second,
+    /// accepts K, M, and G suffixes such as 500K or 2M
+    #[arg(long, value_parser = parse_byte_size)]
+    limit_rate: Option<u64>,
+
+    /// The maximum number of times that a failed download is retried.
+    /// Files that fail with a permanent error, such as a 404, are not retried
+    #[arg(long, default_value_t = 3)]
+    retry_count: usize,
+
+    /// In milliseconds, how long to wait before retrying a failed download.
+    /// Doubles every retry unless the server asks for a specific delay
+    #[arg(long, default_value_t = 500)]
+    retry_delay: u64,
+
+    /// In seconds, how long to wait for a connection to the server
+    #[arg(long, default_value_t = 30)]
+    connect_timeout: u64,
+
+    /// In seconds, how long to wait for data from the server before a download attempt fails
+    #[arg(long, default_value_t = 60)]
+    read_timeout: u64,
+}
+
+impl DownloadArgs {
+    /// Applies these download options to `config`
+    fn apply(&self, config: FetchConfig) -> FetchConfig {
+        config
+            .rate_limit(self.limit_rate)
+            .retry(self.retry_count, self.retry_delay)
+            .timeouts(
+                Some(Duration::from_secs(self.connect_timeout)),
+                Some(Duration::from_secs(self.read_timeout)),
+            )
+    }
+}
+
 pub async fn fetch(args: FetchArgs) -> Result<(), Error> {
     match args.command {
         Commands::Path(args) => path::fetch_path(args).await,
         Commands::Assets(args) => assets::fetch_assets(args).await,
         Commands::List(args) => list::fetch_files_list(args).await,
+        Commands::RetryFailed(args) => retry::retry_failed(args).await,
+        Commands::Import(args) => import::import_assets(args).await,
+        Commands::Watch(args) => watch::fetch_watch(args).await,
+    }
+}
+
+/// Cancels `cancellation_token` when Ctrl-C is pressed,
+/// letting files that are already downloading finish.
+///
+/// Pressing Ctrl-C a second time exits immediately.
+fn cancel_on_ctrl_c(cancellation_token: CancellationToken) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            println!(
+                "{}Cancelling, waiting for in-progress downloads to finish. Press Ctrl-C again to exit immediately.{}",
+                color::ERROR.render_fg(),
+                color::TEXT.render_fg()
+            );
+            cancellation_token.cancel();
+
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+}
+
+/// Prints a message if the download in `summary` was cancelled
+fn print_cancelled(summary: &DownloadSummary) {
+    if summary.cancelled {
+        println!(
+            "{}Download cancelled after downloading {} assets. Run the command again to continue.{}",
+            color::ERROR.render_fg(),
+            summary.downloaded,
+            color::TEXT.render_fg()
+        );
+    }
+}
+
+/// Returns the `fetch retry-failed` command that retries the failed downloads of a fetch
+/// with the same cache, device, and asset directory
+fn retry_failed_command(
+    out_path: &str,
+    cache_path: Option<&str>,
+    device_type: Option<DeviceType>,
+) -> String {
+    let mut command = String::from("starview fetch retry-failed");
+    if let Some(cache_path) = cache_path {
+        command.push_str(&format!(" --cache-path {}", quote_arg(cache_path)));
+    }
+    if let Some(device_type) = device_type {
+        command.push_str(&format!(" --device {}", device_type.name()));
+    }
+    command.push_str(&format!(" {}", quote_arg(out_path)));
+    command
+}
+
+/// Quotes `arg` for a shell if it contains whitespace or quotes
+fn quote_arg(arg: &str) -> String {
+    if arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    } else {
+        arg.into()
+    }
+}
+
+/// Prints every asset that could not be downloaded in `summary`,
+/// along with the `retry_command` that retries them
+fn print_failed_downloads(summary: &DownloadSummary, retry_command: &str) {
+    if summary.failed.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}Failed to download {} assets ({} downloaded):{}",
+        color::ERROR.render_fg(),
+        summary.failed.len(),
+        summary.downloaded,
+        color::TEXT.render_fg()
+    );
+    for failed_download in &summary.failed {
+        println!(
+            "  {} ({} attempts): {}",
+            failed_download.archive.location, failed_download.attempts, failed_download.error
+        );
+    }
+    println!(
+        "Run {}{}{} to retry them.",
+        color::TEXT_VARIANT.render_fg(),
+        retry_command,
+        color::TEXT.render_fg()
+    );
+}
+
+/// Parses a number of bytes with an optional `K`, `M`, or `G` suffix, such as `500K` or `2M`
+fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1024),
+        Some('M') => (&value[..value.len() - 1], 1024 * 1024),
+        Some('G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+
+    let bytes = number
+        .parse::<u64>()
+        .map_err(|err| format!("invalid byte size '{value}': {err}"))?
+        .checked_mul(multiplier)
+        .ok_or(format!("byte size '{value}' is too large"))?;
+
+    if bytes == 0 {
+        Err("byte size must be greater than 0".into())
+    } else {
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("512"), Ok(512));
+        assert_eq!(parse_byte_size("500K"), Ok(500 * 1024));
+        assert_eq!(parse_byte_size("2m"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_byte_size("1G"), Ok(1024 * 1024 * 1024));
+        assert!(parse_byte_size("0").is_err());
+        assert!(parse_byte_size("fast").is_err());
+    }
+
+    #[test]
+    fn test_retry_failed_command() {
+        assert_eq!(
+            retry_failed_command("assets", None, None),
+            "starview fetch retry-failed assets"
+        );
+        assert_eq!(
+            retry_failed_command("my assets", Some("other.cache"), Some(DeviceType::Ios)),
+            "starview fetch retry-failed --cache-path other.cache --device ios 'my assets'"
+        );
     }
 }
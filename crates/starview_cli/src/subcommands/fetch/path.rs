@@ -1,13 +1,17 @@
+use std::path::{Path, PathBuf};
+
 use clap::Parser;
 use starview_common::{enums::DeviceType, fs::write_file};
 use starview_core::fetch::{
     FetchConfig, Fetcher,
     state::{FetchAssetInfoState, FetchState},
 };
-use tokio::{sync::watch, time::Instant};
+use tokio::{sync::mpsc, time::Instant};
+use url::Url;
 
 use crate::{
     Error, color,
+    output::FileFormat,
     progress::{FinishAndClear, ProgressBar},
 };
 
@@ -18,7 +22,7 @@ pub struct Args {
     quiet: bool,
 
     /// The version of the assets,
-    /// uses the latset version by default
+    /// uses the latest version by default
     #[arg(long)]
     asset_version: Option<String>,
 
@@ -31,17 +35,35 @@ pub struct Args {
     #[arg(long, short, value_enum)]
     cache_path: Option<String>,
 
-    /// Where to output the paths file
+    /// Point every archive location at this URL, keeping the rest of each location.
+    ///
+    /// For example, "http://localhost:3001" for assets served by `starview serve-assets`
+    #[arg(long)]
+    rewrite_base: Option<Url>,
+
+    /// How the paths file is written.
+    ///
+    /// Msgpack files contain the whole response of the game server, headers included
+    #[arg(long, short, value_enum, default_value_t = FileFormat::Json)]
+    format: FileFormat,
+
+    /// Where to output the version info of the paths, with the same format and base URL.
+    /// If this is a directory, the file is named after its asset version.
+    /// The version info is not written if this is not set
+    #[arg(long)]
+    version_info_path: Option<String>,
+
+    /// Where to output the paths file.
+    /// If this is a directory, the file is named after its asset version
     out_path: String,
 }
 
-/// Watches a FetchState [`tokio::sync::watch::Receiver`] for any updates,
+/// Receives FetchState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
 /// printing status to the console.
-async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
+async fn watch_fetch_state(mut recv: mpsc::UnboundedReceiver<FetchState>) {
     let mut progress_bar: Option<indicatif::ProgressBar> = None;
 
-    while recv.changed().await.is_ok() {
-        let fetch_state = *recv.borrow_and_update();
+    while let Some(fetch_state) = recv.recv().await {
         if let FetchState::AssetInfo(state) = fetch_state {
             match state {
                 FetchAssetInfoState::GetAssetVersion => {
@@ -70,6 +92,15 @@ async fn watch_fetch_state(mut recv: watch::Receiver<FetchState>) {
     }
 }
 
+/// Returns `path`, or `file_name` in `path` if it is a directory
+fn output_path(path: &str, file_name: &str) -> PathBuf {
+    if Path::new(path).is_dir() {
+        Path::new(path).join(file_name)
+    } else {
+        PathBuf::from(path)
+    }
+}
+
 pub async fn fetch_path(args: Args) -> Result<(), Error> {
     let fetch_start_instant = Instant::now();
     let config = FetchConfig::new(args.cache_path, Some(args.device), None);
@@ -81,17 +112,48 @@ pub async fn fetch_path(args: Args) -> Result<(), Error> {
         Some(tokio::spawn(watch_fetch_state(state_recv)))
     };
 
-    let (_, asset_paths) = fetcher.get_latest_asset_info().await?;
+    let (version_info, mut asset_paths) = fetcher
+        .get_asset_info_or_latest(args.asset_version.as_deref())
+        .await?;
+    if let Some(base) = &args.rewrite_base {
+        asset_paths.rewrite_base(base)?;
+    }
+    // written as the game server sent it, without the device type recorded by the client
+    asset_paths.device_type = None;
+
+    let asset_version = asset_paths.info.client_asset_version.clone();
+    let out_path = output_path(
+        &args.out_path,
+        &format!("{}.{}", asset_version, args.format.extension()),
+    );
+    write_file(&args.format.encode(&asset_paths)?, &out_path).await?;
 
-    let asset_paths = serde_json::to_vec_pretty(&asset_paths)?;
-    write_file(&asset_paths, &args.out_path).await?;
+    if let Some(version_info_path) = &args.version_info_path {
+        // prefer the device's own version info, like the game server would send
+        let mut version_info = version_info
+            .iter()
+            .find(|info| info.device_type == Some(args.device))
+            .or(version_info.first())
+            .ok_or_else(|| Error::MissingVersionInfo(asset_version.clone()))?
+            .clone();
+        if let Some(base) = &args.rewrite_base {
+            version_info.rewrite_base(base)?;
+        }
+        version_info.device_type = None;
+
+        let version_info_path = output_path(
+            version_info_path,
+            &format!("{}.version_info.{}", asset_version, args.format.extension()),
+        );
+        write_file(&args.format.encode(&version_info)?, &version_info_path).await?;
+    }
 
     if let Some(watcher) = state_watcher {
         watcher.await?;
         println!(
             "{}Successfully wrote asset paths to '{}' in {:?}.{}",
             color::SUCCESS.render_fg(),
-            args.out_path,
+            out_path.display(),
             Instant::now().duration_since(fetch_start_instant),
             color::TEXT.render_fg()
         )
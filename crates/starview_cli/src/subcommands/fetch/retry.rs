@@ -0,0 +1,112 @@
+use clap::Parser;
+use starview_common::enums::DeviceType;
+use starview_core::fetch::{
+    FetchConfig, Fetcher,
+    state::{DownloadAssetsState, FetchState},
+};
+use tokio::{sync::mpsc, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    Error, color,
+    progress::{FinishAndClear, ProgressBar},
+};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// If status messages should be displayed
+    #[arg(long, short, default_value_t = false)]
+    quiet: bool,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// The device type that the game's server is logged in as while retrying
+    #[arg(long, short, value_enum)]
+    device: Option<DeviceType>,
+
+    #[command(flatten)]
+    download: super::DownloadArgs,
+
+    /// The maximum number of files to download at once
+    #[arg(long, short, default_value_t = 5)]
+    concurrency: usize,
+
+    /// Path to the directory where assets were downloaded
+    out_path: String,
+}
+
+/// Receives FetchState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
+/// printing status to the console.
+async fn watch_fetch_state(mut recv: mpsc::UnboundedReceiver<FetchState>) {
+    let mut progress_bar: Option<indicatif::ProgressBar> = None;
+
+    while let Some(fetch_state) = recv.recv().await {
+        if let FetchState::DownloadAssets(state) = fetch_state {
+            match state {
+                DownloadAssetsState::DownloadStart(total_bytes) => {
+                    println!(
+                        "{}[1/1] {}Retrying failed downloads...",
+                        color::TEXT_VARIANT.render_fg(),
+                        color::TEXT.render_fg()
+                    );
+                    progress_bar = Some(ProgressBar::download(total_bytes));
+                }
+                DownloadAssetsState::Download(download_state) => {
+                    if let Some(progress) = &progress_bar {
+                        super::assets::print_download_state(progress, download_state);
+                    }
+                }
+                DownloadAssetsState::Finish => {
+                    progress_bar.finish_and_clear();
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub async fn retry_failed(args: Args) -> Result<(), Error> {
+    let retry_start_instant = Instant::now();
+    let cancellation_token = CancellationToken::new();
+    let config = args.download.apply(
+        FetchConfig::new(args.cache_path.clone(), args.device, None)
+            .cancellation_token(cancellation_token.clone()),
+    );
+    let (mut fetcher, state_recv) = Fetcher::new(config).await?;
+
+    let state_watcher = if args.quiet {
+        None
+    } else {
+        Some(tokio::spawn(watch_fetch_state(state_recv)))
+    };
+
+    super::cancel_on_ctrl_c(cancellation_token);
+    let summary = fetcher
+        .retry_failed_downloads(&args.out_path, args.concurrency)
+        .await?;
+
+    if let Some(watcher) = state_watcher {
+        watcher.await?;
+        if summary.failed.is_empty() && !summary.cancelled {
+            println!(
+                "{}Successfully downloaded {} assets to '{}' in {:?}.{}",
+                color::SUCCESS.render_fg(),
+                summary.downloaded,
+                args.out_path,
+                Instant::now().duration_since(retry_start_instant),
+                color::TEXT.render_fg()
+            )
+        }
+    }
+    super::print_cancelled(&summary);
+    super::print_failed_downloads(
+        &summary,
+        &super::retry_failed_command(&args.out_path, args.cache_path.as_deref(), args.device),
+    );
+
+    Ok(())
+}
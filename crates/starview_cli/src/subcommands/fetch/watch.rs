@@ -0,0 +1,329 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Parser;
+use starview_common::{
+    enums::{DeviceType, MirrorLayout},
+    fs::write_file,
+};
+use starview_core::{
+    changelog::Changelog,
+    fetch::{
+        DownloadSummary, FetchConfig, Fetcher,
+        state::{DownloadAssetsState, FetchState, WatchState},
+    },
+};
+use tokio::{process::Command, sync::mpsc};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    Error, color,
+    progress::{FinishAndClear, ProgressBar},
+};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// If status messages should be displayed
+    #[arg(long, short, default_value_t = false)]
+    quiet: bool,
+
+    /// The device type that assets will be acquired for
+    #[arg(long, short, value_enum, default_value_t = DeviceType::All)]
+    device: DeviceType,
+
+    /// How archives are arranged in the asset directory,
+    /// uses the layout of the previous download by default
+    #[arg(long, value_enum)]
+    layout: Option<MirrorLayout>,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    #[command(flatten)]
+    download: super::DownloadArgs,
+
+    /// The maximum number of files to download at once
+    #[arg(long, short, default_value_t = 5)]
+    concurrency: usize,
+
+    /// In seconds, how often the server is polled for a new asset version
+    #[arg(long, default_value_t = 300)]
+    interval: u64,
+
+    /// In seconds, the longest delay between polls when polling keeps failing
+    #[arg(long, default_value_t = 3600)]
+    max_backoff: u64,
+
+    /// Path to the directory where files lists will be downloaded,
+    /// files lists are not downloaded if this is not set
+    #[arg(long)]
+    lists_path: Option<String>,
+
+    /// Path to the directory where a JSON changelog is written for every new asset version,
+    /// changelogs are not written if this is not set
+    #[arg(long)]
+    changelog_path: Option<String>,
+
+    /// A shell command that is run after every new asset version is downloaded.
+    ///
+    /// The command receives STARVIEW_OLD_VERSION, STARVIEW_NEW_VERSION, STARVIEW_ASSETS_PATH,
+    /// STARVIEW_CHANGELOG_PATH, and STARVIEW_FAILED_DOWNLOADS as environment variables.
+    /// STARVIEW_OLD_VERSION is empty for the first download, and STARVIEW_CHANGELOG_PATH is empty
+    /// for the first download or if --changelog-path is not set
+    #[arg(long)]
+    hook: Option<String>,
+
+    /// Path to the directory where assets will be downloaded
+    out_path: String,
+}
+
+/// Receives FetchState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
+/// printing status to the console until the fetcher is dropped.
+async fn watch_fetch_state(mut recv: mpsc::UnboundedReceiver<FetchState>, interval: Duration) {
+    let mut progress_bar: Option<indicatif::ProgressBar> = None;
+
+    while let Some(fetch_state) = recv.recv().await {
+        match fetch_state {
+            FetchState::Watch(WatchState::Unchanged(asset_version)) => {
+                println!(
+                    "Version {} is current, polling again in {:?}.",
+                    asset_version, interval
+                );
+            }
+            FetchState::Watch(WatchState::Relogin) => {
+                println!(
+                    "{}Logging in again...{}",
+                    color::TEXT_VARIANT.render_fg(),
+                    color::TEXT.render_fg()
+                );
+            }
+            FetchState::Watch(WatchState::PollFailed { error, retry_in }) => {
+                println!(
+                    "{}Failed to poll the asset version: {}, polling again in {:?}.{}",
+                    color::ERROR.render_fg(),
+                    error,
+                    retry_in,
+                    color::TEXT.render_fg()
+                );
+            }
+            FetchState::Watch(WatchState::NewVersion(asset_version)) => {
+                println!(
+                    "{}Found new asset version {}.{}",
+                    color::SUCCESS.render_fg(),
+                    asset_version,
+                    color::TEXT.render_fg()
+                );
+            }
+            FetchState::DownloadAssets(DownloadAssetsState::Plan(plan)) => {
+                super::assets::print_download_plan(&plan);
+            }
+            FetchState::DownloadAssets(DownloadAssetsState::DownloadStart(total_bytes)) => {
+                println!(
+                    "{}[1/2] {}Downloading assets...",
+                    color::TEXT_VARIANT.render_fg(),
+                    color::TEXT.render_fg()
+                );
+                progress_bar = Some(ProgressBar::download(total_bytes));
+            }
+            FetchState::DownloadAssets(DownloadAssetsState::Download(download_state)) => {
+                if let Some(progress) = &progress_bar {
+                    super::assets::print_download_state(progress, download_state);
+                }
+            }
+            FetchState::DownloadAssets(DownloadAssetsState::Finish) => {
+                progress_bar.finish_and_clear();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Writes the changelog from `old_version` to `new_version` as JSON to `changelog_path`,
+/// returning the path of the written file
+async fn write_changelog(
+    fetcher: &Fetcher,
+    old_version: &str,
+    new_version: &str,
+    lists_path: Option<&Path>,
+    changelog_path: &Path,
+) -> Result<PathBuf, Error> {
+    let changelog =
+        Changelog::between(fetcher.cache(), old_version, new_version, lists_path).await?;
+
+    let path = changelog_path.join(format!("{old_version}_{new_version}.json"));
+    write_file(&serde_json::to_vec_pretty(&changelog)?, &path).await?;
+    Ok(path)
+}
+
+/// Runs the user's `hook` shell command with information about the new version
+/// in its environment variables
+async fn run_hook(
+    hook: &str,
+    old_version: Option<&str>,
+    new_version: &str,
+    assets_path: &str,
+    changelog_path: Option<&Path>,
+    summary: &DownloadSummary,
+) -> Result<(), Error> {
+    let (shell, shell_arg) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+
+    let status = Command::new(shell)
+        .arg(shell_arg)
+        .arg(hook)
+        .env("STARVIEW_OLD_VERSION", old_version.unwrap_or_default())
+        .env("STARVIEW_NEW_VERSION", new_version)
+        .env("STARVIEW_ASSETS_PATH", assets_path)
+        .env(
+            "STARVIEW_CHANGELOG_PATH",
+            changelog_path.unwrap_or(Path::new("")),
+        )
+        .env(
+            "STARVIEW_FAILED_DOWNLOADS",
+            summary.failed.len().to_string(),
+        )
+        .status()
+        .await?;
+
+    if !status.success() {
+        println!(
+            "{}Hook exited with {}.{}",
+            color::ERROR.render_fg(),
+            status,
+            color::TEXT.render_fg()
+        );
+    }
+    Ok(())
+}
+
+/// Downloads `new_version`, its files lists, and its changelog, then runs the hook.
+///
+/// Returns the download summary.
+async fn update_to_version(
+    fetcher: &mut Fetcher,
+    args: &Args,
+    old_version: Option<&str>,
+    new_version: &str,
+) -> Result<DownloadSummary, Error> {
+    let summary = fetcher
+        .download_assets(Some(new_version), None, &args.out_path, args.concurrency)
+        .await?;
+    if summary.cancelled {
+        return Ok(summary);
+    }
+
+    if let Some(lists_path) = &args.lists_path {
+        if !args.quiet {
+            println!(
+                "{}[2/2] {}Downloading files lists...",
+                color::TEXT_VARIANT.render_fg(),
+                color::TEXT.render_fg()
+            );
+        }
+        fetcher
+            .download_files_list(Some(new_version), lists_path)
+            .await?;
+    }
+
+    let changelog_path = match (old_version, &args.changelog_path) {
+        (Some(old_version), Some(changelog_path)) => Some(
+            write_changelog(
+                fetcher,
+                old_version,
+                new_version,
+                args.lists_path.as_deref().map(Path::new),
+                Path::new(changelog_path),
+            )
+            .await?,
+        ),
+        _ => None,
+    };
+
+    if let Some(hook) = &args.hook {
+        run_hook(
+            hook,
+            old_version,
+            new_version,
+            &args.out_path,
+            changelog_path.as_deref(),
+            &summary,
+        )
+        .await?;
+    }
+
+    Ok(summary)
+}
+
+pub async fn fetch_watch(args: Args) -> Result<(), Error> {
+    let cancellation_token = CancellationToken::new();
+    let config = args.download.apply(
+        FetchConfig::new(args.cache_path.clone(), Some(args.device), None)
+            .layout(args.layout)
+            .cancellation_token(cancellation_token.clone()),
+    );
+    let (mut fetcher, state_recv) = Fetcher::new(config).await?;
+
+    let interval = Duration::from_secs(args.interval);
+    let max_backoff = Duration::from_secs(args.max_backoff);
+    if !args.quiet {
+        tokio::spawn(watch_fetch_state(state_recv, interval));
+    }
+
+    super::cancel_on_ctrl_c(cancellation_token.clone());
+    let mut current_version = fetcher.cache().mirror_version.clone();
+    while let Some(new_version) = fetcher
+        .wait_for_new_asset_version(current_version.as_deref(), interval, max_backoff)
+        .await
+    {
+        match update_to_version(
+            &mut fetcher,
+            &args,
+            current_version.as_deref(),
+            &new_version,
+        )
+        .await
+        {
+            Ok(summary) => {
+                super::print_cancelled(&summary);
+                if summary.cancelled {
+                    break;
+                }
+                super::print_failed_downloads(
+                    &summary,
+                    &super::retry_failed_command(
+                        &args.out_path,
+                        args.cache_path.as_deref(),
+                        Some(args.device),
+                    ),
+                );
+
+                // failed downloads are not retried on every poll,
+                // they are retried by the next version's download or by "fetch retry-failed"
+                current_version = Some(new_version);
+            }
+            Err(err) => {
+                // the version is updated again after the next poll
+                println!(
+                    "{}Failed to update to version {}: {}{}",
+                    color::ERROR.render_fg(),
+                    new_version,
+                    err,
+                    color::TEXT.render_fg()
+                );
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    _ = tokio::time::sleep(interval) => {}
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
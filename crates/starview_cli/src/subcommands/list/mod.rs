@@ -0,0 +1,47 @@
+mod query;
+mod resolve;
+
+use clap::{Args, Subcommand};
+use starview_core::{
+    cache::{DEFAULT_CACHE_PATH, models::FetchCache},
+    files_list::FilesList,
+};
+
+use crate::Error;
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Search the files lists of an asset version
+    Query(query::Args),
+    /// Organize extracted assets by the paths in the files lists
+    Resolve(resolve::Args),
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+pub async fn list(args: ListArgs) -> Result<(), Error> {
+    match args.command {
+        Commands::Query(args) => query::query(args).await,
+        Commands::Resolve(args) => resolve::resolve(args).await,
+    }
+}
+
+/// Loads the files lists of `asset_version` from `lists_path`,
+/// using the version that the assets were last upgraded to if `asset_version` is None.
+async fn load_files_list(
+    cache_path: Option<&str>,
+    asset_version: Option<&str>,
+    lists_path: &str,
+) -> Result<FilesList, Error> {
+    let cache = FetchCache::from_path(cache_path.unwrap_or(DEFAULT_CACHE_PATH))
+        .await
+        .map_err(starview_core::Error::from)?;
+    let asset_version = asset_version
+        .or(cache.default_asset_version())
+        .ok_or(starview_core::Error::NoAssetVersion)?;
+    Ok(FilesList::for_version(&cache, asset_version, lists_path).await?)
+}
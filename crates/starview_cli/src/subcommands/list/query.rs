@@ -0,0 +1,84 @@
+use clap::Parser;
+use starview_common::enums::DeviceType;
+use starview_core::files_list::{FilesListEntry, FilesListQuery, PathPattern};
+
+use crate::{
+    Error,
+    output::{OutputFormat, print_table},
+};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Only show files with a path matching this glob, such as "character/**/*.png"
+    #[arg(long, short, conflicts_with = "regex")]
+    glob: Option<String>,
+
+    /// Only show files with a path matching this regular expression
+    #[arg(long, short)]
+    regex: Option<String>,
+
+    /// Only show files from the files list of this device type
+    #[arg(long, short, value_enum)]
+    device: Option<DeviceType>,
+
+    /// How the matching files are printed
+    #[arg(long, short, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// The version of the files lists,
+    /// uses the version that the assets were last upgraded to by default
+    #[arg(long)]
+    asset_version: Option<String>,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// Path to the directory where files lists were downloaded with "starview fetch list"
+    lists_path: String,
+}
+
+pub async fn query(args: Args) -> Result<(), Error> {
+    let files_list = super::load_files_list(
+        args.cache_path.as_deref(),
+        args.asset_version.as_deref(),
+        &args.lists_path,
+    )
+    .await?;
+
+    let pattern = match (&args.glob, &args.regex) {
+        (Some(glob), _) => Some(PathPattern::glob(glob)?),
+        (None, Some(regex)) => Some(PathPattern::regex(regex)?),
+        (None, None) => None,
+    };
+    let query = FilesListQuery {
+        pattern,
+        device_type: args.device,
+    };
+    let entries: Vec<&FilesListEntry> = files_list.query(&query).collect();
+
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&entries)?),
+        OutputFormat::Table => {
+            let rows: Vec<Vec<String>> = entries
+                .iter()
+                .map(|entry| {
+                    vec![
+                        entry
+                            .device_type
+                            .map(|device_type| device_type.name().to_string())
+                            .unwrap_or_default(),
+                        entry.size.to_string(),
+                        entry.hash.clone(),
+                        entry.path.clone(),
+                        entry.flags.join(","),
+                    ]
+                })
+                .collect();
+            print_table(&["DEVICE", "SIZE", "HASH", "PATH", "FLAGS"], &rows);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use clap::Parser;
+use starview_common::enums::DeviceType;
+use starview_core::files_list::{
+    FilesListEntry, FilesListQuery, LinkMode, ResolveReport, resolve_logical_paths,
+};
+use tokio::time::Instant;
+
+use crate::{
+    Error, color,
+    output::OutputFormat,
+    progress::{FinishAndClear, ProgressBar},
+};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// If status messages should be displayed
+    #[arg(long, short, default_value_t = false)]
+    quiet: bool,
+
+    /// Only resolve files from the files list of this device type.
+    /// If files of multiple device types are resolved, each device gets its own directory
+    #[arg(long, short, value_enum)]
+    device: Option<DeviceType>,
+
+    /// Copy files instead of hardlinking them
+    #[arg(long, default_value_t = false)]
+    copy: bool,
+
+    /// How the report of missing files is printed
+    #[arg(long, short, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// The version of the files lists,
+    /// uses the version that the assets were last upgraded to by default
+    #[arg(long)]
+    asset_version: Option<String>,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// Path to the directory where files lists were downloaded with "starview fetch list"
+    lists_path: String,
+
+    /// Path to the directory where assets were extracted with "starview extract"
+    extracted_path: String,
+
+    /// Path to the directory where files will be organized by their logical paths
+    out_path: String,
+}
+
+/// Prints every entry in `entries` under `heading`
+fn print_entries(heading: &str, entries: &[FilesListEntry]) {
+    if entries.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}{} {}:{}",
+        color::ERROR.render_fg(),
+        entries.len(),
+        heading,
+        color::TEXT.render_fg()
+    );
+    for entry in entries {
+        println!("  {} ({})", entry.path, entry.hash);
+    }
+}
+
+/// Prints `report` in `format`
+fn print_report(report: &ResolveReport, format: OutputFormat) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+        OutputFormat::Table => {
+            print_entries("files are listed but were not found", &report.missing);
+            print_entries(
+                "files have paths outside of the output directory",
+                &report.invalid,
+            );
+        }
+    }
+    Ok(())
+}
+
+pub async fn resolve(args: Args) -> Result<(), Error> {
+    let resolve_start_instant = Instant::now();
+    let files_list = super::load_files_list(
+        args.cache_path.as_deref(),
+        args.asset_version.as_deref(),
+        &args.lists_path,
+    )
+    .await?;
+    let query = FilesListQuery {
+        pattern: None,
+        device_type: args.device,
+    };
+    let entries: Vec<FilesListEntry> = files_list.query(&query).cloned().collect();
+
+    let spinner = if args.quiet {
+        None
+    } else {
+        println!(
+            "{}[1/1] {}Resolving {} files...",
+            color::TEXT_VARIANT.render_fg(),
+            color::TEXT.render_fg(),
+            entries.len()
+        );
+        Some(ProgressBar::spinner())
+    };
+
+    let link_mode = if args.copy {
+        LinkMode::Copy
+    } else {
+        LinkMode::Hardlink
+    };
+    let report =
+        resolve_logical_paths(entries, &args.extracted_path, &args.out_path, link_mode).await?;
+    spinner.finish_and_clear();
+
+    if !args.quiet {
+        println!(
+            "{}Resolved {} files to '{}' in {:?}.{}",
+            color::SUCCESS.render_fg(),
+            report.resolved,
+            args.out_path,
+            Instant::now().duration_since(resolve_start_instant),
+            color::TEXT.render_fg()
+        );
+    }
+    print_report(&report, args.format)
+}
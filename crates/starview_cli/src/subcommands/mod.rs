@@ -1,2 +1,11 @@
+pub mod codec;
+pub mod diff_versions;
+pub mod extract;
 pub mod fetch;
+pub mod list;
 pub mod patch;
+pub mod proxy;
+pub mod prune;
+pub mod serve_api;
+pub mod serve_assets;
+pub mod verify;
@@ -76,10 +76,11 @@ pub fn patch(args: Args) -> Result<(), Error> {
             path.to_path_buf()
         };
 
-        if let Some(parent) = new_path.parent() {
-            if parent.is_dir() && !parent.try_exists()? {
-                create_dir_all(path)?;
-            }
+        if let Some(parent) = new_path.parent()
+            && parent.is_dir()
+            && !parent.try_exists()?
+        {
+            create_dir_all(path)?;
         }
         new_path
     };
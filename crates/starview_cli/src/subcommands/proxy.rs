@@ -0,0 +1,138 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+use starview_net::api_url::API_HOST;
+use starview_server::proxy::{ProxyConfig, ProxyMode, ProxyServer, state::ProxyState};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::{Error, color};
+
+#[derive(Parser, Debug)]
+pub struct RecordArgs {
+    /// The address that the proxy listens on
+    #[arg(long, short, default_value = "127.0.0.1:3000")]
+    address: SocketAddr,
+
+    /// The API server that requests are forwarded to
+    #[arg(long, default_value = API_HOST)]
+    upstream: Url,
+
+    /// Path to the directory where recordings are saved
+    #[arg(long, default_value = "recordings")]
+    recordings_path: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+pub struct ReplayArgs {
+    /// The address that the proxy listens on
+    #[arg(long, short, default_value = "127.0.0.1:3000")]
+    address: SocketAddr,
+
+    /// Path to a recording, or a directory of recordings
+    recordings_path: PathBuf,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Forward requests to the game's API server, saving them and their responses as JSON
+    Record(RecordArgs),
+    /// Answer requests with recorded responses
+    Replay(ReplayArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ProxyArgs {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+/// Receives ProxyState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
+/// printing every request until the proxy is dropped.
+async fn watch_proxy_state(mut recv: mpsc::UnboundedReceiver<ProxyState>) {
+    while let Some(proxy_state) = recv.recv().await {
+        match proxy_state {
+            ProxyState::Listening(address) => {
+                println!(
+                    "{}Listening on {}{}. Press Ctrl-C to stop.",
+                    color::SUCCESS.render_fg(),
+                    address,
+                    color::TEXT.render_fg()
+                );
+            }
+            ProxyState::Recording(path) => {
+                println!("Recording to '{}'.", path.display());
+            }
+            ProxyState::Replaying(exchanges) => {
+                println!("Replaying {exchanges} recorded requests.");
+            }
+            ProxyState::Forwarded { path, status } | ProxyState::Replayed { path, status } => {
+                println!(
+                    "{}{}{} {}",
+                    color::TEXT_VARIANT.render_fg(),
+                    status,
+                    color::TEXT.render_fg(),
+                    path
+                );
+            }
+            ProxyState::Failed {
+                path,
+                status,
+                error,
+            } => {
+                println!(
+                    "{}{}{} {}: {}",
+                    color::ERROR.render_fg(),
+                    status,
+                    color::TEXT.render_fg(),
+                    path,
+                    error
+                );
+            }
+            ProxyState::AcceptFailed(error) => {
+                println!(
+                    "{}Could not accept a connection{}: {}",
+                    color::ERROR.render_fg(),
+                    color::TEXT.render_fg(),
+                    error
+                );
+            }
+            ProxyState::Stopped => break,
+        }
+    }
+}
+
+pub async fn proxy(args: ProxyArgs) -> Result<(), Error> {
+    let (address, mode) = match args.command {
+        Commands::Record(args) => (
+            args.address,
+            ProxyMode::Record {
+                upstream: args.upstream,
+                recordings_path: args.recordings_path,
+            },
+        ),
+        Commands::Replay(args) => (
+            args.address,
+            ProxyMode::Replay {
+                recordings_path: args.recordings_path,
+            },
+        ),
+    };
+
+    let cancellation_token = CancellationToken::new();
+    let config = ProxyConfig::new(address, mode).cancellation_token(cancellation_token.clone());
+
+    let (server, recv) = ProxyServer::new(config).await?;
+    let watcher = tokio::spawn(watch_proxy_state(recv));
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancellation_token.cancel();
+        }
+    });
+
+    let result = server.serve().await;
+    watcher.await?;
+    Ok(result?)
+}
@@ -0,0 +1,105 @@
+use clap::Parser;
+use indicatif::HumanBytes;
+use starview_core::{
+    cache::{DEFAULT_CACHE_PATH, models::FetchCache},
+    mirror::{self, PruneReport},
+};
+
+use crate::{Error, color, output::OutputFormat};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Delete the unreferenced files instead of only listing them
+    #[arg(long, default_value_t = false)]
+    delete: bool,
+
+    /// Keep the archives of this asset version, can be provided multiple times.
+    /// Keeps the version that the assets were last upgraded to by default
+    #[arg(long)]
+    keep: Vec<String>,
+
+    /// Keep the archives of every asset version in the cache
+    #[arg(long, default_value_t = false, conflicts_with = "keep")]
+    keep_all: bool,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// How the unreferenced files are printed
+    #[arg(long, short, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Path to the directory where assets were downloaded
+    assets_path: String,
+}
+
+/// Prints every file in `report` and how much space they use
+fn print_report(report: &PruneReport) {
+    for file in &report.files {
+        println!(
+            "{:>10}  {}",
+            HumanBytes(file.size).to_string(),
+            file.path.display()
+        );
+    }
+
+    let action = if report.deleted { "Deleted" } else { "Found" };
+    println!(
+        "{}{} {} unreferenced files, {} {}.{}",
+        color::SUCCESS.render_fg(),
+        action,
+        report.files.len(),
+        HumanBytes(report.reclaimable_bytes),
+        if report.deleted {
+            "reclaimed"
+        } else {
+            "can be reclaimed"
+        },
+        color::TEXT.render_fg()
+    );
+    if !report.deleted && !report.files.is_empty() {
+        println!(
+            "Run with {}--delete{} to delete them.",
+            color::TEXT_VARIANT.render_fg(),
+            color::TEXT.render_fg()
+        );
+    }
+}
+
+pub async fn prune(args: Args) -> Result<(), Error> {
+    let cache_path = args.cache_path.as_deref().unwrap_or(DEFAULT_CACHE_PATH);
+    let mut cache = FetchCache::from_path(cache_path)
+        .await
+        .map_err(starview_core::Error::from)?;
+
+    let keep_versions: Vec<String> = if args.keep_all {
+        cache.versions.keys().cloned().collect()
+    } else if args.keep.is_empty() {
+        vec![
+            cache
+                .default_asset_version()
+                .ok_or(starview_core::Error::NoAssetVersion)?
+                .to_string(),
+        ]
+    } else {
+        args.keep
+    };
+    let keep_versions: Vec<&str> = keep_versions.iter().map(String::as_str).collect();
+
+    let report = mirror::prune(&mut cache, &keep_versions, &args.assets_path, args.delete).await?;
+    if report.deleted {
+        cache
+            .write(cache_path)
+            .await
+            .map_err(starview_core::Error::from)?;
+    }
+
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Table => print_report(&report),
+    }
+
+    Ok(())
+}
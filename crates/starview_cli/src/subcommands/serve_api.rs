@@ -0,0 +1,117 @@
+use std::net::SocketAddr;
+
+use clap::Parser;
+use starview_core::cache::{DEFAULT_CACHE_PATH, models::FetchCache};
+use starview_server::api::{ApiConfig, ApiServer, state::ApiState};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::{Error, color};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The address that the server listens on
+    #[arg(long, short, default_value = "127.0.0.1:3000")]
+    address: SocketAddr,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// The asset version that clients are told to use,
+    /// the latest cached asset version by default
+    #[arg(long)]
+    asset_version: Option<String>,
+
+    /// Point asset URLs at this URL, keeping the rest of each URL.
+    ///
+    /// For example, "http://localhost:3001" for assets served by `starview serve-assets`
+    #[arg(long)]
+    rewrite_base: Option<Url>,
+
+    /// Accept requests without checking their checksum
+    #[arg(long, default_value_t = false)]
+    no_verify_checksum: bool,
+}
+
+/// Receives ApiState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
+/// printing every request until the server is dropped.
+async fn watch_api_state(mut recv: mpsc::UnboundedReceiver<ApiState>, asset_version: String) {
+    while let Some(api_state) = recv.recv().await {
+        match api_state {
+            ApiState::Listening(address) => {
+                println!(
+                    "{}Listening on {}{}, serving asset version {}. Press Ctrl-C to stop.",
+                    color::SUCCESS.render_fg(),
+                    address,
+                    color::TEXT.render_fg(),
+                    asset_version
+                );
+            }
+            ApiState::Request {
+                path,
+                status,
+                error: None,
+            } => {
+                println!(
+                    "{}{}{} {}",
+                    color::TEXT_VARIANT.render_fg(),
+                    status,
+                    color::TEXT.render_fg(),
+                    path
+                );
+            }
+            ApiState::Request {
+                path,
+                status,
+                error: Some(error),
+            } => {
+                println!(
+                    "{}{}{} {}: {}",
+                    color::ERROR.render_fg(),
+                    status,
+                    color::TEXT.render_fg(),
+                    path,
+                    error
+                );
+            }
+            ApiState::AcceptFailed(error) => {
+                println!(
+                    "{}Could not accept a connection{}: {}",
+                    color::ERROR.render_fg(),
+                    color::TEXT.render_fg(),
+                    error
+                );
+            }
+            ApiState::Stopped => break,
+        }
+    }
+}
+
+pub async fn serve_api(args: Args) -> Result<(), Error> {
+    let cache = FetchCache::from_path(args.cache_path.as_deref().unwrap_or(DEFAULT_CACHE_PATH))
+        .await
+        .map_err(starview_core::Error::from)?;
+
+    let cancellation_token = CancellationToken::new();
+    let config = ApiConfig::new(args.address, cache)
+        .asset_version(args.asset_version)
+        .rewrite_base(args.rewrite_base)
+        .verify_checksum(!args.no_verify_checksum)
+        .cancellation_token(cancellation_token.clone());
+
+    let (server, recv) = ApiServer::new(config)?;
+    let watcher = tokio::spawn(watch_api_state(recv, server.asset_version().to_string()));
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancellation_token.cancel();
+        }
+    });
+
+    let result = server.serve().await;
+    watcher.await?;
+    Ok(result?)
+}
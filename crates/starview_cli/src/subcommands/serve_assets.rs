@@ -0,0 +1,120 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::Parser;
+use starview_core::cache::{DEFAULT_CACHE_PATH, models::FetchCache};
+use starview_server::assets::{AssetServer, AssetsConfig, state::AssetState};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::{Error, color};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The address that the server listens on
+    #[arg(long, short, default_value = "127.0.0.1:3001")]
+    address: SocketAddr,
+
+    /// Path to the starview cache that the mirror was downloaded with,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// Path to the directory where files lists were downloaded by `starview fetch list`,
+    /// files lists are looked up in the mirror if this is not set
+    #[arg(long)]
+    lists_path: Option<PathBuf>,
+
+    /// Only print requests for files that are not in the mirror
+    #[arg(long, default_value_t = false)]
+    misses_only: bool,
+
+    /// Path to the directory where assets were downloaded
+    mirror_path: String,
+}
+
+/// Receives AssetState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
+/// printing requests until the server is dropped.
+async fn watch_asset_state(mut recv: mpsc::UnboundedReceiver<AssetState>, misses_only: bool) {
+    while let Some(asset_state) = recv.recv().await {
+        match asset_state {
+            AssetState::Listening(address) => {
+                println!(
+                    "{}Listening on {}{}. Press Ctrl-C to stop.",
+                    color::SUCCESS.render_fg(),
+                    address,
+                    color::TEXT.render_fg()
+                );
+            }
+            AssetState::Served {
+                path,
+                status,
+                bytes,
+            } if !misses_only => {
+                println!(
+                    "{}{}{} {} ({})",
+                    color::TEXT_VARIANT.render_fg(),
+                    status,
+                    color::TEXT.render_fg(),
+                    path,
+                    indicatif::HumanBytes(bytes)
+                );
+            }
+            AssetState::Miss(path) => {
+                println!(
+                    "{}Miss{} {}",
+                    color::ERROR.render_fg(),
+                    color::TEXT.render_fg(),
+                    path
+                );
+            }
+            AssetState::Failed {
+                path,
+                status,
+                error,
+            } => {
+                println!(
+                    "{}{}{} {}: {}",
+                    color::ERROR.render_fg(),
+                    status,
+                    color::TEXT.render_fg(),
+                    path,
+                    error
+                );
+            }
+            AssetState::AcceptFailed(error) => {
+                println!(
+                    "{}Could not accept a connection{}: {}",
+                    color::ERROR.render_fg(),
+                    color::TEXT.render_fg(),
+                    error
+                );
+            }
+            AssetState::Stopped => break,
+            _ => {}
+        }
+    }
+}
+
+pub async fn serve_assets(args: Args) -> Result<(), Error> {
+    let cache = FetchCache::from_path(args.cache_path.as_deref().unwrap_or(DEFAULT_CACHE_PATH))
+        .await
+        .map_err(starview_core::Error::from)?;
+
+    let cancellation_token = CancellationToken::new();
+    let config = AssetsConfig::new(args.address, &args.mirror_path, cache)
+        .lists_path(args.lists_path)
+        .cancellation_token(cancellation_token.clone());
+
+    let (server, recv) = AssetServer::new(config);
+    let watcher = tokio::spawn(watch_asset_state(recv, args.misses_only));
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancellation_token.cancel();
+        }
+    });
+
+    let result = server.serve().await;
+    watcher.await?;
+    Ok(result?)
+}
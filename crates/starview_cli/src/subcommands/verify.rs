@@ -0,0 +1,186 @@
+use clap::Parser;
+use starview_core::{
+    cache::{DEFAULT_CACHE_PATH, models::FetchCache},
+    mirror::{ArchiveStatus, Verifier, VerifyReport, state::VerifyState},
+};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    Error, color,
+    output::OutputFormat,
+    progress::{FinishAndClear, ProgressBar},
+};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// If status messages should be displayed
+    #[arg(long, short, default_value_t = false)]
+    quiet: bool,
+
+    /// Only verify the archives of this asset version,
+    /// verifies the archives of every fetched version by default
+    #[arg(long)]
+    asset_version: Option<String>,
+
+    /// Path to the starview cache,
+    /// "starview.cache" by default
+    #[arg(long)]
+    cache_path: Option<String>,
+
+    /// The maximum number of archives to hash at once
+    #[arg(long, short, default_value_t = 4)]
+    concurrency: usize,
+
+    /// Update the cache so that the next "starview fetch assets"
+    /// downloads the missing and corrupt archives again
+    #[arg(long, default_value_t = false)]
+    update_cache: bool,
+
+    /// How the report is printed
+    #[arg(long, short, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Path to the directory where assets were downloaded
+    assets_path: String,
+}
+
+/// Receives VerifyState updates from a [`tokio::sync::mpsc::UnboundedReceiver`],
+/// printing status to the console.
+async fn watch_verify_state(mut recv: mpsc::UnboundedReceiver<VerifyState>) {
+    let mut progress_bar: Option<indicatif::ProgressBar> = None;
+
+    while let Some(verify_state) = recv.recv().await {
+        match verify_state {
+            VerifyState::VerifyStart(total_bytes) => {
+                println!(
+                    "{}[1/2] {}Verifying archives...",
+                    color::TEXT_VARIANT.render_fg(),
+                    color::TEXT.render_fg()
+                );
+                progress_bar = Some(ProgressBar::download(total_bytes));
+            }
+            VerifyState::ArchiveVerify { archive, status } => {
+                if let Some(progress) = &progress_bar {
+                    progress.inc(archive.size);
+                    if let ArchiveStatus::Corrupt(reason) = status {
+                        progress.println(format!(
+                            "{}Corrupt archive '{}': {}{}",
+                            color::ERROR.render_fg(),
+                            archive.location,
+                            reason,
+                            color::TEXT.render_fg()
+                        ));
+                    }
+                }
+            }
+            VerifyState::FindExtraFiles => {
+                progress_bar.finish_and_clear();
+                println!(
+                    "{}[2/2] {}Searching for extra files...",
+                    color::TEXT_VARIANT.render_fg(),
+                    color::TEXT.render_fg()
+                );
+                progress_bar = Some(ProgressBar::spinner());
+            }
+            VerifyState::Finish => {
+                progress_bar.finish_and_clear();
+                break;
+            }
+        }
+    }
+}
+
+/// Prints `report` as a list of every archive and file that is not valid
+fn print_report(report: &VerifyReport) {
+    if report.is_clean() {
+        println!(
+            "{}All {} archives are valid.{}",
+            color::SUCCESS.render_fg(),
+            report.valid,
+            color::TEXT.render_fg()
+        );
+        return;
+    }
+
+    println!(
+        "{} valid, {} missing, {} corrupt, {} extra files.",
+        report.valid,
+        report.missing.len(),
+        report.corrupt.len(),
+        report.extra.len()
+    );
+    for archive in &report.missing {
+        println!(
+            "{}missing{}  {}",
+            color::ERROR.render_fg(),
+            color::TEXT.render_fg(),
+            archive.location
+        );
+    }
+    for corrupt in &report.corrupt {
+        println!(
+            "{}corrupt{}  {} ({})",
+            color::ERROR.render_fg(),
+            color::TEXT.render_fg(),
+            corrupt.archive.location,
+            corrupt.reason
+        );
+    }
+    for path in &report.extra {
+        println!(
+            "{}extra{}    {}",
+            color::TEXT_VARIANT.render_fg(),
+            color::TEXT.render_fg(),
+            path.display()
+        );
+    }
+}
+
+pub async fn verify(args: Args) -> Result<(), Error> {
+    let verify_start_instant = Instant::now();
+    let cache_path = args.cache_path.as_deref().unwrap_or(DEFAULT_CACHE_PATH);
+    let mut cache = FetchCache::from_path(cache_path)
+        .await
+        .map_err(starview_core::Error::from)?;
+
+    let (verifier, state_recv) = Verifier::for_cache(
+        &cache,
+        args.asset_version.as_deref(),
+        &args.assets_path,
+        args.concurrency,
+    )?;
+
+    let state_watcher = if args.quiet {
+        None
+    } else {
+        Some(tokio::spawn(watch_verify_state(state_recv)))
+    };
+
+    let report = verifier.verify().await?;
+
+    if args.update_cache {
+        report.apply_to_cache(&mut cache);
+        cache
+            .write(cache_path)
+            .await
+            .map_err(starview_core::Error::from)?;
+    }
+
+    if let Some(watcher) = state_watcher {
+        watcher.await?;
+        println!(
+            "{}Verified '{}' in {:?}.{}",
+            color::SUCCESS.render_fg(),
+            args.assets_path,
+            Instant::now().duration_since(verify_start_instant),
+            color::TEXT.render_fg()
+        );
+    }
+
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Table => print_report(&report),
+    }
+
+    Ok(())
+}
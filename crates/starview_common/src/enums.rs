@@ -1,3 +1,5 @@
+use std::fmt;
+
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
@@ -7,28 +9,85 @@ pub enum AssetSize {
     Short,
 }
 
-impl ToString for AssetSize {
-    fn to_string(&self) -> String {
+impl fmt::Display for AssetSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AssetSize::Full => "fulfill".into(),
-            AssetSize::Short => "shortened".into(),
+            AssetSize::Full => write!(f, "fulfill"),
+            AssetSize::Short => write!(f, "shortened"),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum DeviceType {
     Ios,
     Android,
     All,
 }
 
-impl ToString for DeviceType {
-    fn to_string(&self) -> String {
+impl DeviceType {
+    /// Returns the lowercase name of this device type, as it is written on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceType::Ios => "ios",
+            DeviceType::Android => "android",
+            DeviceType::All => "all",
+        }
+    }
+
+    /// Returns the devices that this device type stands for,
+    /// both Android and iOS for `All`
+    pub fn devices(&self) -> &'static [DeviceType] {
+        match self {
+            DeviceType::Ios => &[DeviceType::Ios],
+            DeviceType::Android => &[DeviceType::Android],
+            DeviceType::All => &[DeviceType::Android, DeviceType::Ios],
+        }
+    }
+
+    /// Parses the code that the game sends for a device type, the inverse of [`fmt::Display`]
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "1" => Some(DeviceType::Ios),
+            "2" => Some(DeviceType::Android),
+            "3" => Some(DeviceType::All),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceType::Ios => write!(f, "1"),
+            DeviceType::Android => write!(f, "2"),
+            DeviceType::All => write!(f, "3"),
+        }
+    }
+}
+
+/// How archives are arranged in a directory of downloaded assets
+#[derive(Debug, Clone, Copy, Default, ValueEnum, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorLayout {
+    /// Archives of every device share a single tree, at their path on the CDN
+    #[default]
+    Merged,
+    /// Each device has its own tree in a directory named after the device,
+    /// archives used by both devices are hardlinked between the trees
+    PerDevice,
+    /// Archives are stored once, named after their sha256,
+    /// with an index of each asset version's archives for every device
+    ContentAddressed,
+}
+
+impl MirrorLayout {
+    /// Returns the name of this layout, as it is written on the command line
+    pub fn name(&self) -> &'static str {
         match self {
-            DeviceType::Ios => "1".into(),
-            DeviceType::Android => "2".into(),
-            DeviceType::All => "3".into(),
+            MirrorLayout::Merged => "merged",
+            MirrorLayout::PerDevice => "per-device",
+            MirrorLayout::ContentAddressed => "content-addressed",
         }
     }
 }
@@ -1,24 +1,72 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use tokio::{
-    fs::{File, create_dir_all},
+    fs::{File, create_dir_all, remove_file, rename},
     io::AsyncWriteExt,
 };
 
+/// Counter used to give every temporary file written by this process a unique name
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
 /// Writes the given bytes to the file at `path`
 ///
-/// Overwrites and truncates existing files
+/// The bytes are written to a temporary file in the same directory,
+/// which is synced to disk and then renamed to `path`.
+/// If writing is interrupted, any existing file at `path` is left untouched.
 pub async fn write_file(data: &[u8], path: impl AsRef<Path>) -> Result<(), std::io::Error> {
-    // write file
-    if let Some(parent) = path.as_ref().parent() {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
         create_dir_all(parent).await?;
     }
-    let mut out_file = File::options()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(path)
-        .await?;
-    out_file.write_all(data).await?;
-    Ok(())
+
+    let temp_path = get_temp_path(path);
+    let write_result = async {
+        let mut temp_file = File::create(&temp_path).await?;
+        temp_file.write_all(data).await?;
+        temp_file.sync_all().await?;
+        rename(&temp_path, path).await
+    }
+    .await;
+
+    if write_result.is_err() {
+        let _ = remove_file(&temp_path).await;
+    }
+    write_result
+}
+
+/// Returns a unique path in the same directory as `path`
+/// that a file can be written to before being renamed to `path`
+fn get_temp_path(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("nested").join("file.json");
+
+        write_file(b"first", &path).await.unwrap();
+        write_file(b"second", &path).await.unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        // no temporary files should be left behind
+        let file_count = std::fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(file_count, 1);
+    }
 }
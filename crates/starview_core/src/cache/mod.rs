@@ -1 +1,4 @@
 pub mod models;
+
+/// Where the fetch cache is stored when no path is provided
+pub const DEFAULT_CACHE_PATH: &str = "starview.cache";
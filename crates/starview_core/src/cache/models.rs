@@ -1,34 +1,67 @@
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use serde::{Deserialize, Serialize};
-use starview_common::{enums::DeviceType, fs::write_file};
-use starview_net::models::{AssetPaths, AssetVersionInfo};
+use starview_common::{
+    enums::{DeviceType, MirrorLayout},
+    fs::write_file,
+};
+use starview_net::models::{AssetPathArchive, AssetPaths, AssetVersionInfo};
 use tokio::{fs::File, io::AsyncReadExt};
 
-use crate::error::FetchCacheError;
+use crate::{error::FetchCacheError, fetch::DownloadPlan};
 
 /// Cache that stores information related to the game server, such as user ID, asset paths, and more.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct FetchCache {
     pub device_type: DeviceType,
     pub udid: String,
-    pub version_info: Vec<AssetVersionInfo>,
-    pub asset_paths: Option<AssetPaths>,
+    /// Asset info for every asset version that has been fetched, keyed by asset version
+    #[serde(default)]
+    pub versions: BTreeMap<String, CachedVersion>,
+    /// The most recent asset version that the game server reported
+    #[serde(default)]
+    pub latest_asset_version: Option<String>,
+    /// The asset version that the downloaded assets were last fully upgraded to
+    #[serde(default)]
+    pub mirror_version: Option<String>,
+    /// How the archives are arranged in the directory of downloaded assets
+    #[serde(default)]
+    pub mirror_layout: MirrorLayout,
+    /// Version info from caches written before multiple versions were stored,
+    /// moved into `versions` when the cache is loaded
+    #[serde(default, skip_serializing)]
+    version_info: Vec<AssetVersionInfo>,
+    /// Asset paths from caches written before multiple versions were stored,
+    /// moved into `versions` when the cache is loaded
+    #[serde(default, skip_serializing)]
+    asset_paths: Option<AssetPaths>,
     /// A hash set containing the sha256 of assets that have already been downloaded
     pub downloaded_asset_hashes: HashSet<String>,
+    /// Assets that could not be downloaded, keyed by their location
+    #[serde(default)]
+    pub failed_downloads: HashMap<String, FailedDownload>,
 }
 
 impl FetchCache {
     /// Creates a new FetchCache with the provided udid
     ///
-    /// `version_info` and `asset_paths` will be None
+    /// The cache will not contain any asset versions
     pub fn new(udid: String, device_type: DeviceType) -> Self {
         Self {
             udid,
             device_type,
+            versions: BTreeMap::new(),
+            latest_asset_version: None,
+            mirror_version: None,
+            mirror_layout: MirrorLayout::default(),
             version_info: Vec::new(),
             asset_paths: None,
             downloaded_asset_hashes: HashSet::new(),
+            failed_downloads: HashMap::new(),
         }
     }
 
@@ -38,10 +71,121 @@ impl FetchCache {
         let cache_file_metadata = cache_file.metadata().await?;
         let mut file_bytes = Vec::with_capacity(cache_file_metadata.len().try_into()?);
         cache_file.read_to_end(&mut file_bytes).await?;
-        let fetch_cache: Self = serde_json::from_slice(&file_bytes)?;
+        let mut fetch_cache: Self = serde_json::from_slice(&file_bytes)?;
+        fetch_cache.migrate_single_version();
         Ok(fetch_cache)
     }
 
+    /// Moves the asset info of a cache that only stored a single version into `versions`
+    fn migrate_single_version(&mut self) {
+        if let Some(asset_paths) = self.asset_paths.take() {
+            let asset_version = asset_paths.info.client_asset_version.clone();
+            self.versions
+                .entry(asset_version.clone())
+                .or_insert_with(|| CachedVersion {
+                    device_type: self.device_type,
+                    version_info: std::mem::take(&mut self.version_info),
+                    asset_paths,
+                    device_asset_paths: Vec::new(),
+                    fetched_at: 0,
+                    applied_plan: None,
+                });
+            self.latest_asset_version.get_or_insert(asset_version);
+        }
+    }
+
+    /// Returns the asset version that commands working with downloaded assets use by default.
+    ///
+    /// This is the version the assets were last fully upgraded to,
+    /// or the latest version if they were never fully upgraded.
+    pub fn default_asset_version(&self) -> Option<&str> {
+        self.mirror_version
+            .as_deref()
+            .or(self.latest_asset_version.as_deref())
+    }
+
+    /// Returns the archives that build `asset_version` in a mirror, in the order they are applied,
+    /// using the asset paths of `device_type` if they were kept.
+    ///
+    /// A version that was downloaded uses the diffs that its download applied on top of
+    /// the archives of the version it was upgraded from. Any other version uses the archives
+    /// that a download from the full archives fetches, see [`DownloadPlan`].
+    ///
+    /// Returns None if `asset_version` is not cached.
+    pub fn mirror_archives(
+        &self,
+        asset_version: &str,
+        device_type: Option<DeviceType>,
+    ) -> Option<Vec<AssetPathArchive>> {
+        // walk back through the versions that each download upgraded from
+        let mut upgrades: Vec<(&AssetPaths, &AppliedPlan)> = Vec::new();
+        let mut version = asset_version;
+        let mut archives = loop {
+            let cached_version = self.versions.get(version)?;
+            let asset_paths = cached_version.asset_paths_of(device_type);
+            let Some(plan) = &cached_version.applied_plan else {
+                break DownloadPlan::new(asset_paths, None).archives;
+            };
+            match &plan.from_version {
+                // the length check stops a cycle of upgrades from looping forever
+                Some(from_version)
+                    if self.versions.contains_key(from_version)
+                        && upgrades.len() < self.versions.len() =>
+                {
+                    upgrades.push((asset_paths, plan));
+                    version = from_version;
+                }
+                Some(_) => break DownloadPlan::new(asset_paths, None).archives,
+                None => {
+                    break asset_paths
+                        .full
+                        .archive
+                        .iter()
+                        .chain(applied_diff_archives(
+                            asset_paths,
+                            &asset_paths.full.version,
+                            &plan.diff_versions,
+                        ))
+                        .cloned()
+                        .collect();
+                }
+            }
+        };
+
+        for (asset_paths, plan) in upgrades.into_iter().rev() {
+            let from_version = plan.from_version.as_deref().unwrap_or_default();
+            archives.extend(
+                applied_diff_archives(asset_paths, from_version, &plan.diff_versions).cloned(),
+            );
+        }
+        Some(archives)
+    }
+
+    /// Returns the sha256 of every archive referenced by a cached asset version
+    pub fn referenced_asset_hashes(&self) -> HashSet<String> {
+        self.versions
+            .values()
+            .flat_map(|version| version.archives())
+            .map(|archive| archive.sha256.clone())
+            .collect()
+    }
+
+    /// Returns the devices that use each archive of every cached asset version, keyed by sha256
+    pub fn archive_devices(&self) -> HashMap<String, Vec<DeviceType>> {
+        let mut archive_devices: HashMap<String, Vec<DeviceType>> = HashMap::new();
+        for version in self.versions.values() {
+            for (sha256, device_types) in version.archive_devices() {
+                let known_device_types = archive_devices.entry(sha256.into()).or_default();
+                for device_type in device_types {
+                    if !known_device_types.contains(&device_type) {
+                        known_device_types.push(device_type);
+                    }
+                }
+            }
+        }
+        archive_devices
+    }
+
     /// Writes this FetchCache to a file at the specified path
     pub async fn write(&self, path: impl AsRef<Path>) -> Result<(), FetchCacheError> {
         let cache_bytes = serde_json::to_vec(self)?;
@@ -49,3 +193,229 @@ impl FetchCache {
         Ok(())
     }
 }
+
+/// The asset info for a single asset version
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CachedVersion {
+    /// The device type that the asset info was fetched for
+    pub device_type: DeviceType,
+    pub version_info: Vec<AssetVersionInfo>,
+    /// The asset paths of every device merged together
+    pub asset_paths: AssetPaths,
+    /// The asset paths of each device before they were merged into `asset_paths`.
+    ///
+    /// Empty for versions fetched before the asset paths of each device were kept
+    #[serde(default)]
+    pub device_asset_paths: Vec<AssetPaths>,
+    /// Unix timestamp in seconds of when the asset info was fetched
+    #[serde(default)]
+    pub fetched_at: u64,
+    /// The download that last completed this version in the mirror,
+    /// None if this version was never completely downloaded
+    #[serde(default)]
+    pub applied_plan: Option<AppliedPlan>,
+}
+
+/// The diffs that a completed download applied to bring a mirror to an asset version
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedPlan {
+    /// The version that the mirror was upgraded from,
+    /// None if the full archives were downloaded
+    pub from_version: Option<String>,
+    /// The versions of the applied diffs, in the order they were applied
+    pub diff_versions: Vec<String>,
+}
+
+impl From<&DownloadPlan> for AppliedPlan {
+    fn from(plan: &DownloadPlan) -> Self {
+        Self {
+            from_version: plan.from_version.clone(),
+            diff_versions: plan.diff_versions.clone(),
+        }
+    }
+}
+
+impl CachedVersion {
+    /// Creates a new CachedVersion that was fetched now
+    pub fn new(
+        device_type: DeviceType,
+        version_info: Vec<AssetVersionInfo>,
+        asset_paths: AssetPaths,
+    ) -> Self {
+        Self {
+            device_type,
+            version_info,
+            asset_paths,
+            device_asset_paths: Vec::new(),
+            fetched_at: unix_timestamp(),
+            applied_plan: None,
+        }
+    }
+
+    /// Sets the asset paths of each device that were merged into this version's asset paths
+    pub fn device_asset_paths(mut self, device_asset_paths: Vec<AssetPaths>) -> Self {
+        self.device_asset_paths = device_asset_paths;
+        self
+    }
+
+    /// Returns the asset paths of `device_type` if they were kept,
+    /// or the asset paths of every device merged together otherwise
+    pub fn asset_paths_of(&self, device_type: Option<DeviceType>) -> &AssetPaths {
+        device_type
+            .and_then(|device_type| {
+                self.device_asset_paths
+                    .iter()
+                    .find(|asset_paths| asset_paths.device_type == Some(device_type))
+            })
+            .unwrap_or(&self.asset_paths)
+    }
+
+    /// Returns every full and diff archive of this version
+    pub fn archives(&self) -> impl Iterator<Item = &AssetPathArchive> {
+        asset_paths_archives(&self.asset_paths)
+    }
+
+    /// Returns the devices that use each archive of this version, keyed by sha256.
+    ///
+    /// If the asset paths of each device were not kept,
+    /// every archive is used by every device that the version was fetched for.
+    pub fn archive_devices(&self) -> HashMap<&str, Vec<DeviceType>> {
+        let mut archive_devices: HashMap<&str, Vec<DeviceType>> = HashMap::new();
+        if self.device_asset_paths.is_empty() {
+            for archive in self.archives() {
+                archive_devices.insert(&archive.sha256, self.device_type.devices().to_vec());
+            }
+            return archive_devices;
+        }
+
+        for asset_paths in &self.device_asset_paths {
+            let Some(device_type) = asset_paths.device_type else {
+                continue;
+            };
+            for archive in asset_paths_archives(asset_paths) {
+                let device_types = archive_devices.entry(&archive.sha256).or_default();
+                if !device_types.contains(&device_type) {
+                    device_types.push(device_type);
+                }
+            }
+        }
+        archive_devices
+    }
+}
+
+/// Returns every full and diff archive of `asset_paths`
+fn asset_paths_archives(asset_paths: &AssetPaths) -> impl Iterator<Item = &AssetPathArchive> {
+    asset_paths
+        .full
+        .archive
+        .iter()
+        .chain(asset_paths.diff.iter().flat_map(|diff| &diff.archive))
+}
+
+/// Returns the archives of the diffs in `asset_paths` that apply `diff_versions` in order,
+/// starting from `from_version`
+fn applied_diff_archives<'a>(
+    asset_paths: &'a AssetPaths,
+    from_version: &str,
+    diff_versions: &'a [String],
+) -> impl Iterator<Item = &'a AssetPathArchive> {
+    let mut previous_version = from_version.to_string();
+    diff_versions
+        .iter()
+        .filter_map(move |version| {
+            // diffs that were not chained were applied by version alone
+            let diff = asset_paths
+                .diff
+                .iter()
+                .find(|diff| diff.version == *version && diff.original_version == previous_version)
+                .or_else(|| {
+                    asset_paths
+                        .diff
+                        .iter()
+                        .find(|diff| diff.version == *version)
+                });
+            previous_version = version.clone();
+            diff.map(|diff| diff.archive.iter())
+        })
+        .flatten()
+}
+
+/// An asset that could not be downloaded
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FailedDownload {
+    pub archive: AssetPathArchive,
+    /// The error from the most recent attempt
+    pub error: String,
+    /// The total number of times that the download was attempted
+    pub attempts: usize,
+    /// Unix timestamp in seconds of the most recent attempt
+    pub failed_at: u64,
+}
+
+impl FailedDownload {
+    /// Creates a new FailedDownload for `archive` that has not been attempted yet
+    pub fn new(archive: AssetPathArchive) -> Self {
+        Self {
+            archive,
+            error: String::new(),
+            attempts: 0,
+            failed_at: 0,
+        }
+    }
+
+    /// Records that the download failed again after `attempts` more attempts
+    pub fn record_failure(&mut self, error: String, attempts: usize) {
+        self.error = error;
+        self.attempts += attempts;
+        self.failed_at = unix_timestamp();
+    }
+}
+
+/// Returns the current unix timestamp in seconds
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_migrate_single_version() {
+        let archive = json!({"location": "/full/a.zip", "size": 1, "sha256": "aa"});
+        let legacy_cache = json!({
+            "device_type": DeviceType::Android,
+            "udid": "udid",
+            "version_info": [],
+            "asset_paths": {
+                "info": {
+                    "client_asset_version": "1.0.1",
+                    "target_asset_version": "1.0.1",
+                    "eventual_target_asset_version": "1.0.1",
+                    "is_initial": false,
+                    "latest_maj_first_version": "1.0.0"
+                },
+                "full": {"version": "1.0.0", "archive": [archive]},
+                "diff": [],
+                "asset_version_hash": ""
+            },
+            "downloaded_asset_hashes": ["aa"]
+        });
+
+        let mut cache: FetchCache = serde_json::from_value(legacy_cache).unwrap();
+        cache.migrate_single_version();
+
+        assert!(cache.asset_paths.is_none());
+        assert_eq!(cache.latest_asset_version.as_deref(), Some("1.0.1"));
+        assert!(cache.versions["1.0.1"].device_type == DeviceType::Android);
+        assert_eq!(
+            cache.referenced_asset_hashes(),
+            HashSet::from(["aa".to_string()])
+        );
+    }
+}
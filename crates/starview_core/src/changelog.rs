@@ -0,0 +1,174 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    path::Path,
+};
+
+use serde::Serialize;
+use starview_common::enums::DeviceType;
+use starview_net::models::AssetPathArchive;
+
+use crate::{
+    Error,
+    cache::models::FetchCache,
+    files_list::{FilesList, FilesListEntry},
+};
+
+/// An item that exists in both versions but is different
+#[derive(Clone, Debug, Serialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// The items that were added, removed, or changed between two versions
+#[derive(Clone, Debug, Serialize)]
+pub struct Changes<T> {
+    pub added: Vec<T>,
+    pub removed: Vec<T>,
+    pub changed: Vec<Change<T>>,
+}
+
+impl<T: Clone> Changes<T> {
+    /// Compares `old` and `new`, matching items with the same `key`
+    /// and treating matched items as changed if `is_same` returns false.
+    ///
+    /// Added and changed items are in the order of `new`, removed items are in the order of `old`.
+    fn between<K: Eq + Hash>(
+        old: &[T],
+        new: &[T],
+        key: impl Fn(&T) -> K,
+        is_same: impl Fn(&T, &T) -> bool,
+    ) -> Self {
+        let old_items: HashMap<K, &T> = old.iter().map(|item| (key(item), item)).collect();
+        let new_keys: HashSet<K> = new.iter().map(&key).collect();
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        for new_item in new {
+            match old_items.get(&key(new_item)) {
+                None => added.push(new_item.clone()),
+                Some(old_item) if !is_same(old_item, new_item) => changed.push(Change {
+                    old: (*old_item).clone(),
+                    new: new_item.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        let removed = old
+            .iter()
+            .filter(|old_item| !new_keys.contains(&key(old_item)))
+            .cloned()
+            .collect();
+
+        Self {
+            added,
+            removed,
+            changed,
+        }
+    }
+
+    /// Returns true if nothing was added, removed, or changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// What changed between two cached asset versions
+#[derive(Clone, Debug, Serialize)]
+pub struct Changelog {
+    pub old_version: String,
+    pub new_version: String,
+    /// Full and diff archives, matched by location
+    pub archives: Changes<AssetPathArchive>,
+    /// Files list entries, matched by device type and path.
+    ///
+    /// None if the files lists were not compared
+    pub files: Option<Changes<FilesListEntry>>,
+}
+
+impl Changelog {
+    /// Compares the archives of two asset versions in `cache`.
+    ///
+    /// If `lists_path` is provided, the files lists of both versions are loaded from it
+    /// and their entries are compared as well.
+    pub async fn between(
+        cache: &FetchCache,
+        old_version: &str,
+        new_version: &str,
+        lists_path: Option<&Path>,
+    ) -> Result<Self, Error> {
+        let old_archives = Self::archives(cache, old_version)?;
+        let new_archives = Self::archives(cache, new_version)?;
+        let archives = Changes::between(
+            &old_archives,
+            &new_archives,
+            |archive| archive.location.clone(),
+            |old, new| old.size == new.size && old.sha256.eq_ignore_ascii_case(&new.sha256),
+        );
+
+        let files = match lists_path {
+            Some(lists_path) => {
+                let old_list = FilesList::for_version(cache, old_version, lists_path).await?;
+                let new_list = FilesList::for_version(cache, new_version, lists_path).await?;
+                Some(Changes::between(
+                    &old_list.entries,
+                    &new_list.entries,
+                    |entry| -> (Option<DeviceType>, String) {
+                        (entry.device_type, entry.path.clone())
+                    },
+                    |old, new| old.size == new.size && old.hash == new.hash,
+                ))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            old_version: old_version.into(),
+            new_version: new_version.into(),
+            archives,
+            files,
+        })
+    }
+
+    /// Returns every archive of `asset_version` in `cache`
+    fn archives(cache: &FetchCache, asset_version: &str) -> Result<Vec<AssetPathArchive>, Error> {
+        Ok(cache
+            .versions
+            .get(asset_version)
+            .ok_or_else(|| Error::UnknownAssetVersion(asset_version.into()))?
+            .archives()
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(location: &str, sha256: &str) -> AssetPathArchive {
+        AssetPathArchive {
+            location: location.into(),
+            size: 1,
+            sha256: sha256.into(),
+        }
+    }
+
+    #[test]
+    fn test_changes_between() {
+        let old = vec![archive("a", "1"), archive("b", "1"), archive("c", "1")];
+        let new = vec![archive("d", "1"), archive("b", "2"), archive("c", "1")];
+        let changes = Changes::between(
+            &old,
+            &new,
+            |archive| archive.location.clone(),
+            |old, new| old.sha256 == new.sha256,
+        );
+
+        assert_eq!(changes.added[0].location, "d");
+        assert_eq!(changes.removed[0].location, "a");
+        assert_eq!(changes.changed.len(), 1);
+        assert_eq!(changes.changed[0].new.sha256, "2");
+    }
+}
@@ -1,19 +1,38 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
+use crate::download::{DownloadFile, RateLimiter};
+
 /// Configuration options for a Downloader
 pub struct DownloadConfig {
     /// In milliseconds, how long between retries.
     ///
-    /// This value will increase exponentially every retry
+    /// This value will increase exponentially every retry,
+    /// unless the server asks for a specific delay with a `Retry-After` header
     pub retry_delay: u64,
+    /// The maximum number of times that a download that failed with a transient error is retried
     pub retry_count: usize,
+    /// How long to wait for a connection to the server before giving up
+    pub connect_timeout: Option<Duration>,
+    /// How long to wait for data from the server before giving up
+    pub read_timeout: Option<Duration>,
     pub out_path: PathBuf,
-    pub urls: Vec<Url>,
+    pub files: Vec<DownloadFile>,
     pub concurrency: usize,
     /// When a downloaded file is saved, this is stripped from the beginning of the out path
     pub url_strip_prefix: Option<String>,
+    /// If partially downloaded files should be resumed instead of downloaded again
+    pub resume: bool,
+    /// Limits the download speed of all files downloaded with this config
+    pub rate_limiter: RateLimiter,
+    /// When cancelled, no new files will be downloaded,
+    /// but files that are already downloading will finish
+    pub cancellation_token: CancellationToken,
 }
 
 impl DownloadConfig {
@@ -28,10 +47,15 @@ impl Default for DownloadConfig {
         Self {
             retry_delay: 500,
             retry_count: 3,
+            connect_timeout: None,
+            read_timeout: None,
             out_path: PathBuf::new(),
-            urls: Vec::new(),
+            files: Vec::new(),
             concurrency: 5,
             url_strip_prefix: None,
+            resume: false,
+            rate_limiter: RateLimiter::default(),
+            cancellation_token: CancellationToken::new(),
         }
     }
 }
@@ -48,12 +72,24 @@ impl DownloadConfigBuilder {
         self
     }
 
-    /// The maxmimum number of times that a download will be retried
+    /// The maximum number of times that a download will be retried
     pub fn retry_count(mut self, retry_count: usize) -> Self {
         self.config.retry_count = retry_count;
         self
     }
 
+    /// Sets how long to wait for a connection to the server before an attempt fails
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets how long to wait for data from the server before an attempt fails
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.read_timeout = timeout;
+        self
+    }
+
     /// Where downloaded files will be saved to
     pub fn out_path(mut self, path: impl AsRef<Path>) -> Self {
         self.config.out_path = path.as_ref().to_path_buf();
@@ -61,8 +97,17 @@ impl DownloadConfigBuilder {
     }
 
     /// The URLs of the files that will be downloaded
+    ///
+    /// Files downloaded from these URLs will not be verified
     pub fn urls(mut self, urls: Vec<Url>) -> Self {
-        self.config.urls = urls;
+        self.config.files = urls.into_iter().map(DownloadFile::from).collect();
+        self
+    }
+
+    /// The files that will be downloaded,
+    /// verifying their size and sha256 digest if they were provided
+    pub fn files(mut self, files: Vec<DownloadFile>) -> Self {
+        self.config.files = files;
         self
     }
 
@@ -78,6 +123,33 @@ impl DownloadConfigBuilder {
         self
     }
 
+    /// Sets whether partially downloaded files will be resumed
+    ///
+    /// When enabled, files are requested from the end of their existing part file
+    /// using a `Range` header. If the server ignores it, the whole file is downloaded again.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.config.resume = resume;
+        self
+    }
+
+    /// Limits the combined download speed of all files to `bytes_per_sec` bytes per second
+    pub fn rate_limit(mut self, bytes_per_sec: Option<u64>) -> Self {
+        self.config.rate_limiter = RateLimiter::new(bytes_per_sec);
+        self
+    }
+
+    /// Uses an existing rate limiter, sharing its limit with every other user of the limiter
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.config.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Sets the token that stops new files from being downloaded when it is cancelled
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.config.cancellation_token = cancellation_token;
+        self
+    }
+
     /// Builds a DownloadConfig from this builder
     pub fn build(self) -> DownloadConfig {
         self.config
@@ -1,57 +1,128 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
 use crate::{
     Error,
-    download::{DownloadConfig, state::DownloadState},
+    download::{
+        DownloadConfig, DownloadFailure, DownloadFile, RateLimiter,
+        retry::{RetryPolicy, parse_retry_after},
+        state::DownloadState,
+    },
+};
+use futures_util::{StreamExt, stream};
+use reqwest::{Client, Response, StatusCode, header::RANGE};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs::{File, create_dir_all, metadata, remove_file, rename},
+    io::{AsyncReadExt, AsyncWriteExt, BufWriter},
+    sync::mpsc,
+    time::sleep,
 };
-use futures_util::{FutureExt, StreamExt, future::BoxFuture, stream};
-use reqwest::Client;
-use starview_common::fs::write_file;
-use tokio::sync::watch;
-use tokio_retry::{Action, Retry, strategy::ExponentialBackoff};
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
+/// Size of the buffer used when writing a downloaded file to disk
+const WRITE_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Extension appended to a file's out path while it is being downloaded
+const PART_EXTENSION: &str = "part";
+
 /// Interface for downloading multiple files concurrently
 pub struct Downloader {
-    state_sender: watch::Sender<DownloadState>,
+    state_sender: mpsc::UnboundedSender<DownloadState>,
     config: DownloadConfig,
     client: Client,
 }
 
 impl Downloader {
-    pub fn new(config: DownloadConfig) -> (Self, watch::Receiver<DownloadState>) {
-        let (state_sender, recv) = watch::channel(DownloadState::NotStarted);
+    /// Creates a new Downloader with the provided config.
+    ///
+    /// Every state update is sent to the returned receiver.
+    pub fn new(
+        config: DownloadConfig,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<DownloadState>), Error> {
+        let (state_sender, recv) = mpsc::unbounded_channel();
 
-        (
+        let mut client_builder = Client::builder();
+        if let Some(connect_timeout) = config.connect_timeout {
+            client_builder = client_builder.connect_timeout(connect_timeout);
+        }
+        if let Some(read_timeout) = config.read_timeout {
+            client_builder = client_builder.read_timeout(read_timeout);
+        }
+
+        Ok((
             Self {
                 state_sender,
                 config,
-                client: Client::new(),
+                client: client_builder.build()?,
             },
             recv,
-        )
+        ))
     }
 
-    /// Downloads a file from `url` and saves it to `out_path`
+    /// Returns a handle to this Downloader's rate limiter.
     ///
-    /// Returns the url and the number of bytes that were downloaded
-    async fn download_file(
-        client: Client,
-        url: Url,
-        out_path: PathBuf,
-    ) -> Result<(Url, u64), Error> {
-        let request = client.get(url.as_str());
+    /// The handle can be used to change the download speed limit while files are downloading.
+    pub fn rate_limiter(&self) -> RateLimiter {
+        self.config.rate_limiter.clone()
+    }
 
-        match request.send().await?.error_for_status() {
-            Ok(response) => {
-                let bytes = response.bytes().await?;
-                write_file(&bytes, out_path).await?;
-                Ok((url, bytes.len().try_into()?))
+    /// Sends a GET request to `url`.
+    ///
+    /// If `resume_from` is not 0, only the bytes starting at that offset are requested.
+    async fn send_request(client: &Client, url: &Url, resume_from: u64) -> Result<Response, Error> {
+        let mut request = client.get(url.as_str());
+        if resume_from > 0 {
+            request = request.header(RANGE, format!("bytes={resume_from}-"));
+        }
+        Ok(request.send().await?)
+    }
+
+    /// Hashes the contents of the file at `path`
+    pub(crate) async fn hash_file(path: &Path) -> Result<Sha256, Error> {
+        let mut file = File::open(path).await?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; WRITE_BUFFER_SIZE];
+        loop {
+            let read_bytes = file.read(&mut buffer).await?;
+            if read_bytes == 0 {
+                break;
             }
-            Err(err) => Err(Error::StarviewNet(starview_net::Error::InvalidRequest(
-                err.to_string(),
-            ))),
+            hasher.update(&buffer[..read_bytes]);
         }
+        Ok(hasher)
+    }
+
+    /// Confirms that a downloaded file matches the expected size and digest of `file`
+    fn verify_file(file: &DownloadFile, size: u64, sha256: &str) -> Result<(), Error> {
+        if let Some(expected_size) = file.size
+            && expected_size != size
+        {
+            return Err(Error::SizeMismatch {
+                url: file.url.to_string(),
+                expected: expected_size,
+                actual: size,
+            });
+        }
+
+        if let Some(expected_sha256) = &file.sha256
+            && !expected_sha256.eq_ignore_ascii_case(sha256)
+        {
+            return Err(Error::ChecksumMismatch {
+                url: file.url.to_string(),
+                expected: expected_sha256.clone(),
+                actual: sha256.into(),
+            });
+        }
+
+        Ok(())
     }
 
     /// Calculates where a file downloaded from a URL should be saved.
@@ -59,7 +130,11 @@ impl Downloader {
     /// This function removes the host from `url` and appends it onto `out_dir`.
     ///
     /// If `strip_prefix` was provided, that prefix will be stripped from `url` before being appended.
-    fn get_url_out_path(url: &Url, out_dir: &Path, strip_prefix: &Option<String>) -> PathBuf {
+    pub(crate) fn get_url_out_path(
+        url: &Url,
+        out_dir: &Path,
+        strip_prefix: &Option<String>,
+    ) -> PathBuf {
         let url_path = url.path();
 
         let stripped_url_path = strip_prefix
@@ -77,59 +152,105 @@ impl Downloader {
         out_dir.join(relative_url_path)
     }
 
+    /// Returns the path that a file is written to while it is being downloaded
+    pub(crate) fn get_part_path(out_path: &Path) -> PathBuf {
+        let mut part_path = out_path.as_os_str().to_owned();
+        part_path.push(".");
+        part_path.push(PART_EXTENSION);
+        PathBuf::from(part_path)
+    }
+
     /// Downloads all urls that were given to this Downloader.
     ///
+    /// Files that fail with a transient error are retried with an exponential backoff.
+    /// Files that fail with a permanent error, like a 404, are not retried.
+    ///
+    /// If the config's cancellation token is cancelled, files that have not started downloading
+    /// are skipped, and the files that are already downloading are allowed to finish
+    /// without being retried.
+    ///
     /// On success, returns a tuple containing:
     /// - urls that were successfully downloaded
-    /// - download errors
-    pub async fn download(self) -> Result<(Vec<Url>, Vec<Error>), Error> {
+    /// - files that could not be downloaded
+    pub async fn download(self) -> Result<(Vec<Url>, Vec<DownloadFailure>), Error> {
         // generate out_paths
-        let to_download_urls: Vec<(Url, PathBuf)> = self
+        let to_download_files: Vec<(DownloadFile, PathBuf)> = self
             .config
-            .urls
+            .files
             .into_iter()
-            .map(|url| {
-                let out_path = Self::get_url_out_path(
-                    &url,
-                    &self.config.out_path,
-                    &self.config.url_strip_prefix,
-                );
-                (url, out_path)
+            .map(|file| {
+                let out_path = file.out_path.clone().unwrap_or_else(|| {
+                    Self::get_url_out_path(
+                        &file.url,
+                        &self.config.out_path,
+                        &self.config.url_strip_prefix,
+                    )
+                });
+                (file, out_path)
             })
             .collect();
 
         // send download start state update
-        self.state_sender
-            .send_replace(DownloadState::DownloadStart(to_download_urls.len()));
+        let _ = self
+            .state_sender
+            .send(DownloadState::DownloadStart(to_download_files.len()));
 
         // download files
-        let retry_strategy =
-            ExponentialBackoff::from_millis(self.config.retry_delay).take(self.config.retry_count);
-        let download_results: Vec<Result<Url, Error>> = stream::iter(to_download_urls)
-            .map(|(url, out_path)| {
-                let retry_strategy = retry_strategy.clone();
+        let retry_policy = RetryPolicy {
+            retry_count: self.config.retry_count,
+            retry_delay: Duration::from_millis(self.config.retry_delay),
+        };
+        let download_results: Vec<Result<Url, DownloadFailure>> = stream::iter(to_download_files)
+            .take_until(self.config.cancellation_token.clone().cancelled_owned())
+            .map(|(file, out_path)| {
+                let retry_policy = retry_policy.clone();
+                let cancellation_token = self.config.cancellation_token.clone();
                 let client = self.client.clone();
                 let state_sender = self.state_sender.clone();
+                let rate_limiter = self.config.rate_limiter.clone();
+                let resume = self.config.resume;
                 async move {
-                    let download_result = Retry::spawn(
-                        retry_strategy,
-                        DownloadAction {
-                            client,
-                            url,
-                            out_path,
-                        },
-                    )
+                    let url = file.url.clone();
+                    let attempts = Arc::new(AtomicUsize::new(0));
+                    let _ = state_sender.send(DownloadState::FileStart {
+                        url: url.clone(),
+                        out_path: out_path.clone(),
+                    });
+
+                    let download_result = DownloadAction {
+                        client,
+                        file,
+                        out_path: out_path.clone(),
+                        resume,
+                        state_sender: state_sender.clone(),
+                        rate_limiter,
+                        attempts: attempts.clone(),
+                    }
+                    .download_with_retry(&retry_policy, &cancellation_token)
                     .await;
 
                     // send file download/error state update
                     match download_result {
-                        Ok((url, bytes_size)) => {
-                            state_sender.send_replace(DownloadState::FileDownload(bytes_size));
+                        Ok((url, size)) => {
+                            let _ = state_sender.send(DownloadState::FileDownload {
+                                url: url.clone(),
+                                out_path,
+                                size,
+                            });
                             Ok(url)
                         }
                         Err(err) => {
-                            state_sender.send_replace(DownloadState::DownloadError);
-                            Err(err)
+                            let _ = state_sender.send(DownloadState::DownloadError {
+                                url: url.clone(),
+                                out_path: out_path.clone(),
+                                error: err.to_string(),
+                            });
+                            Err(DownloadFailure {
+                                url,
+                                out_path,
+                                error: err,
+                                attempts: attempts.load(Ordering::Relaxed),
+                            })
                         }
                     }
                 }
@@ -140,34 +261,267 @@ impl Downloader {
 
         // filter errors out of download_results
         let mut downloaded_urls: Vec<Url> = Vec::new();
-        let mut download_errors: Vec<Error> = Vec::new();
+        let mut download_failures: Vec<DownloadFailure> = Vec::new();
         for download_result in download_results {
             match download_result {
                 Ok(url) => downloaded_urls.push(url),
-                Err(err) => download_errors.push(err),
+                Err(failure) => download_failures.push(failure),
             }
         }
 
         // send finish state
-        self.state_sender.send_replace(DownloadState::Finish);
+        let _ = self.state_sender.send(DownloadState::Finish);
 
-        Ok((downloaded_urls, download_errors))
+        Ok((downloaded_urls, download_failures))
     }
 }
 
 struct DownloadAction {
     client: Client,
-    url: Url,
+    file: DownloadFile,
     out_path: PathBuf,
+    /// Whether an existing part file should be resumed
+    resume: bool,
+    state_sender: mpsc::UnboundedSender<DownloadState>,
+    rate_limiter: RateLimiter,
+    /// The number of times that this file has been attempted
+    attempts: Arc<AtomicUsize>,
 }
 
-impl Action for DownloadAction {
-    type Future = BoxFuture<'static, Result<Self::Item, Self::Error>>;
-    type Item = (Url, u64);
-    type Error = Error;
+impl DownloadAction {
+    /// Calls [`DownloadAction::download_file`] until it succeeds,
+    /// or until `retry_policy` decides that the error it failed with should not be retried.
+    ///
+    /// Waiting for a retry stops early if `cancellation_token` is cancelled.
+    async fn download_with_retry(
+        &self,
+        retry_policy: &RetryPolicy,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(Url, u64), Error> {
+        loop {
+            let attempts = self.attempts.fetch_add(1, Ordering::Relaxed) + 1;
+            let err = match self.download_file().await {
+                Ok(downloaded) => return Ok(downloaded),
+                Err(err) => err,
+            };
+
+            let Some(delay) = retry_policy.next_delay(&err, attempts) else {
+                return Err(err);
+            };
+            if cancellation_token.is_cancelled() {
+                return Err(err);
+            }
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = cancellation_token.cancelled() => return Err(err),
+            }
+        }
+    }
+
+    /// Downloads `file` and streams it to a part file next to `out_path` chunk by chunk,
+    /// moving it to `out_path` once the download completes.
+    ///
+    /// If `resume` is true and a part file already exists,
+    /// only the remaining bytes are requested from the server,
+    /// and a part file that already has every byte is verified without a request.
+    ///
+    /// If the file's size or sha256 digest are known,
+    /// the downloaded file is verified against them.
+    ///
+    /// Returns the url and the number of bytes that were downloaded
+    async fn download_file(&self) -> Result<(Url, u64), Error> {
+        let part_path = Downloader::get_part_path(&self.out_path);
+        let mut written_bytes: u64 = 0;
+
+        if let Err(err) = self.write_part_file(&part_path, &mut written_bytes).await {
+            // keep the part file if it can be resumed,
+            // but never keep a file that failed verification
+            let verify_failed = matches!(
+                err,
+                Error::ChecksumMismatch { .. } | Error::SizeMismatch { .. }
+            );
+            if !self.resume || verify_failed {
+                let _ = remove_file(&part_path).await;
+            }
+
+            let _ = self.state_sender.send(DownloadState::FileAttemptFailed {
+                url: self.file.url.clone(),
+                error: err.to_string(),
+                discarded_bytes: written_bytes,
+            });
+            return Err(err);
+        }
+
+        rename(&part_path, &self.out_path).await?;
+        Ok((self.file.url.clone(), written_bytes))
+    }
+
+    /// Downloads `file` to `part_path`, verifying it once it has been fully written.
+    ///
+    /// `written_bytes` is set to the number of bytes that were reported as progress.
+    async fn write_part_file(
+        &self,
+        part_path: &Path,
+        written_bytes: &mut u64,
+    ) -> Result<(), Error> {
+        let mut resume_from = if self.resume {
+            metadata(part_path)
+                .await
+                .map(|meta| meta.len())
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        // a part file with every byte would be answered with 416, so it is verified as it is
+        if resume_from > 0 && self.file.size == Some(resume_from) {
+            let digest = hex::encode(Downloader::hash_file(part_path).await?.finalize());
+            if Downloader::verify_file(&self.file, resume_from, &digest).is_ok() {
+                *written_bytes = resume_from;
+                self.send_progress(resume_from);
+                return Ok(());
+            }
+            resume_from = 0;
+        }
+
+        let mut response =
+            Downloader::send_request(&self.client, &self.file.url, resume_from).await?;
+        if resume_from > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // the part file can not be resumed, so the whole file is downloaded again
+            resume_from = 0;
+            response = Downloader::send_request(&self.client, &self.file.url, 0).await?;
+        }
+
+        if !response.status().is_success() {
+            return Err(Error::HttpStatus {
+                url: self.file.url.to_string(),
+                status: response.status().as_u16(),
+                retry_after: parse_retry_after(response.headers()),
+            });
+        }
+
+        // the server may ignore the range header,
+        // in which case the whole file is being downloaded again
+        let resumed = resume_from > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+        let (out_file, hasher) = if resumed {
+            let hasher = Downloader::hash_file(part_path).await?;
+            let out_file = File::options().append(true).open(part_path).await?;
+            *written_bytes = resume_from;
+            self.send_progress(resume_from);
+            (out_file, hasher)
+        } else {
+            if let Some(parent) = part_path.parent() {
+                create_dir_all(parent).await?;
+            }
+            (File::create(part_path).await?, Sha256::new())
+        };
+
+        let digest = self
+            .write_response(response, out_file, hasher, written_bytes)
+            .await?;
+        Downloader::verify_file(&self.file, *written_bytes, &digest)
+    }
+
+    /// Sends a progress update for this action's file
+    fn send_progress(&self, bytes: u64) {
+        let _ = self.state_sender.send(DownloadState::FileProgress {
+            url: self.file.url.clone(),
+            bytes,
+        });
+    }
+
+    /// Writes the body of `response` to `out_file` as it is received,
+    /// adding the size of each chunk to `written_bytes` and sending it as progress
+    ///
+    /// `hasher` should already contain any bytes that were written to `out_file` previously.
+    ///
+    /// Returns the sha256 digest of the file as a hex string
+    async fn write_response(
+        &self,
+        mut response: Response,
+        out_file: File,
+        mut hasher: Sha256,
+        written_bytes: &mut u64,
+    ) -> Result<String, Error> {
+        let mut writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, out_file);
+        while let Some(chunk) = response.chunk().await? {
+            let chunk_size: u64 = chunk.len().try_into()?;
+            self.rate_limiter.acquire(chunk_size).await;
+            writer.write_all(&chunk).await?;
+            hasher.update(&chunk);
+            *written_bytes += chunk_size;
+            self.send_progress(chunk_size);
+        }
+        writer.flush().await?;
+        // the file must be on disk before it is renamed to its final path
+        writer.get_ref().sync_all().await?;
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_get_url_out_path() {
+        let url = Url::from_str("https://cdn.example.com/patch/gf/upload_assets/a/b.zip").unwrap();
+        let out_path = Downloader::get_url_out_path(
+            &url,
+            Path::new("out"),
+            &Some("/patch/gf/upload_assets".into()),
+        );
+        assert_eq!(out_path, Path::new("out").join("a/b.zip"));
+    }
+
+    #[test]
+    fn test_get_part_path() {
+        let part_path = Downloader::get_part_path(Path::new("out/a/b.zip"));
+        assert_eq!(part_path, Path::new("out/a/b.zip.part"));
+    }
+
+    #[tokio::test]
+    async fn test_complete_part_file_is_not_downloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("file.zip");
+        std::fs::write(Downloader::get_part_path(&out_path), "hello").unwrap();
+
+        // nothing listens on the discard port, so a request would fail
+        let url = Url::from_str("http://127.0.0.1:9/file.zip").unwrap();
+        let sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let (state_sender, _) = mpsc::unbounded_channel();
+        let action = DownloadAction {
+            client: Client::new(),
+            file: DownloadFile::new(url).sha256(sha256.into()).size(5),
+            out_path: out_path.clone(),
+            resume: true,
+            state_sender,
+            rate_limiter: RateLimiter::new(None),
+            attempts: Arc::new(AtomicUsize::new(0)),
+        };
+
+        let (_, bytes) = action.download_file().await.unwrap();
+        assert_eq!(bytes, 5);
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "hello");
+        assert!(!Downloader::get_part_path(&out_path).exists());
+    }
+
+    #[test]
+    fn test_verify_file() {
+        let url = Url::from_str("https://cdn.example.com/file.zip").unwrap();
+        let sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let file = DownloadFile::new(url).sha256(sha256.into()).size(5);
 
-    fn run(&mut self) -> Self::Future {
-        Downloader::download_file(self.client.clone(), self.url.clone(), self.out_path.clone())
-            .boxed()
+        assert!(Downloader::verify_file(&file, 5, &sha256.to_uppercase()).is_ok());
+        assert!(matches!(
+            Downloader::verify_file(&file, 4, sha256),
+            Err(Error::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            Downloader::verify_file(&file, 5, "00"),
+            Err(Error::ChecksumMismatch { .. })
+        ));
     }
 }
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use url::Url;
+
+use crate::Error;
+
+/// A file that will be downloaded by a [`crate::download::Downloader`]
+#[derive(Clone, Debug)]
+pub struct DownloadFile {
+    pub url: Url,
+    /// The expected sha256 digest of the file as a hex string
+    pub sha256: Option<String>,
+    /// The expected size of the file in bytes
+    pub size: Option<u64>,
+    /// Where the file is saved,
+    /// overriding the path generated from the URL and the downloader's out path
+    pub out_path: Option<PathBuf>,
+}
+
+impl DownloadFile {
+    /// Creates a new DownloadFile that will not be verified after downloading
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            sha256: None,
+            size: None,
+            out_path: None,
+        }
+    }
+
+    /// Sets the sha256 digest that the downloaded file must match
+    pub fn sha256(mut self, sha256: String) -> Self {
+        self.sha256 = Some(sha256);
+        self
+    }
+
+    /// Sets the size in bytes that the downloaded file must match
+    pub fn size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Sets where the file is saved instead of generating a path from its URL
+    pub fn out_path(mut self, out_path: PathBuf) -> Self {
+        self.out_path = Some(out_path);
+        self
+    }
+}
+
+impl From<Url> for DownloadFile {
+    fn from(url: Url) -> Self {
+        Self::new(url)
+    }
+}
+
+/// A file that could not be downloaded by a [`crate::download::Downloader`]
+#[derive(Debug)]
+pub struct DownloadFailure {
+    pub url: Url,
+    pub out_path: PathBuf,
+    /// The error from the final download attempt
+    pub error: Error,
+    /// The number of times the download was attempted
+    pub attempts: usize,
+}
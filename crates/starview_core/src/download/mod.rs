@@ -1,7 +1,12 @@
 mod config;
 mod downloader;
+mod file;
+mod rate_limit;
+mod retry;
 
 pub mod state;
 
-pub use config::DownloadConfig;
+pub use config::{DownloadConfig, DownloadConfigBuilder};
 pub use downloader::Downloader;
+pub use file::{DownloadFailure, DownloadFile};
+pub use rate_limit::RateLimiter;
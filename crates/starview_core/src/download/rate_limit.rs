@@ -0,0 +1,113 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use tokio::time::sleep;
+
+/// Token bucket that limits the number of bytes downloaded per second.
+///
+/// Clones share the same bucket, so one limiter can throttle every concurrent download.
+/// The limit can be changed at any time with [`RateLimiter::set_limit`].
+#[derive(Clone, Debug, Default)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    /// Creates a new RateLimiter that allows `bytes_per_sec` bytes per second.
+    ///
+    /// If `bytes_per_sec` is None, the limiter does not limit anything.
+    pub fn new(bytes_per_sec: Option<u64>) -> Self {
+        let limiter = Self::default();
+        limiter.set_limit(bytes_per_sec);
+        limiter
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TokenBucket> {
+        self.bucket.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Changes the number of bytes allowed per second.
+    ///
+    /// None or 0 removes the limit.
+    pub fn set_limit(&self, bytes_per_sec: Option<u64>) {
+        self.lock()
+            .set_limit(bytes_per_sec.filter(|&limit| limit > 0), Instant::now());
+    }
+
+    /// The number of bytes allowed per second, if there is a limit
+    pub fn limit(&self) -> Option<u64> {
+        self.lock().bytes_per_sec
+    }
+
+    /// Waits until `bytes` bytes can be used without exceeding the limit
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = self.lock().take(bytes, Instant::now());
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TokenBucket {
+    bytes_per_sec: Option<u64>,
+    /// Available bytes. Negative when more bytes were taken than were available.
+    tokens: f64,
+    last_refill: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn set_limit(&mut self, bytes_per_sec: Option<u64>, now: Instant) {
+        self.bytes_per_sec = bytes_per_sec;
+        // a new limit starts with a full bucket
+        self.tokens = bytes_per_sec.unwrap_or(0) as f64;
+        self.last_refill = Some(now);
+    }
+
+    /// Refills the bucket and takes `bytes` tokens from it.
+    ///
+    /// Returns how long the caller must wait before the taken bytes are within the limit.
+    fn take(&mut self, bytes: u64, now: Instant) -> Duration {
+        let Some(bytes_per_sec) = self.bytes_per_sec else {
+            return Duration::ZERO;
+        };
+        let rate = bytes_per_sec as f64;
+
+        let elapsed = self
+            .last_refill
+            .map(|last_refill| now.saturating_duration_since(last_refill).as_secs_f64())
+            .unwrap_or(0.0);
+        // at most one second of bytes can be saved up
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.last_refill = Some(now);
+
+        self.tokens -= bytes as f64;
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_take() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::default();
+        assert_eq!(bucket.take(1000, start), Duration::ZERO);
+
+        bucket.set_limit(Some(1000), start);
+        assert_eq!(bucket.take(1000, start), Duration::ZERO);
+        assert_eq!(bucket.take(500, start), Duration::from_millis(500));
+
+        // half a second later, the 500 byte debt has been paid off
+        let later = start + Duration::from_millis(500);
+        assert_eq!(bucket.take(1000, later), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::{Duration, SystemTime};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use tokio_retry::strategy::jitter;
+
+use crate::Error;
+
+/// The longest time that is waited between retries when the server did not ask for a delay
+const MAX_BACKOFF_DELAY: Duration = Duration::from_secs(60);
+
+/// Decides if and when a failed download should be attempted again
+#[derive(Clone, Debug)]
+pub(crate) struct RetryPolicy {
+    /// The maximum number of times that a download will be retried
+    pub retry_count: usize,
+    /// How long to wait before the first retry.
+    ///
+    /// This value doubles every retry
+    pub retry_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Returns how long to wait before attempting a download again
+    /// after `attempts` attempts failed with `error`.
+    ///
+    /// Returns None if the download should not be retried.
+    pub fn next_delay(&self, error: &Error, attempts: usize) -> Option<Duration> {
+        if attempts > self.retry_count || !error.is_transient() {
+            return None;
+        }
+
+        match error.retry_after() {
+            Some(retry_after) => Some(retry_after),
+            None => Some(self.backoff_delay(attempts)),
+        }
+    }
+
+    /// Exponential backoff with half of the delay randomized,
+    /// so that files which failed together are not retried together
+    fn backoff_delay(&self, attempts: usize) -> Duration {
+        let exponent: u32 = attempts.saturating_sub(1).try_into().unwrap_or(u32::MAX);
+        let delay = self
+            .retry_delay
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(MAX_BACKOFF_DELAY);
+        delay / 2 + jitter(delay / 2)
+    }
+}
+
+/// Parses the `Retry-After` header of a response.
+///
+/// The header can either be a number of seconds or an HTTP date.
+pub(crate) fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn status_error(status: u16, retry_after: Option<Duration>) -> Error {
+        Error::HttpStatus {
+            url: "https://cdn.example.com/file.zip".into(),
+            status,
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn test_next_delay() {
+        let policy = RetryPolicy {
+            retry_count: 2,
+            retry_delay: Duration::from_millis(100),
+        };
+
+        // permanent errors are never retried
+        assert_eq!(policy.next_delay(&status_error(404, None), 1), None);
+
+        let delay = policy.next_delay(&status_error(503, None), 2).unwrap();
+        assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+
+        let retry_after = Some(Duration::from_secs(7));
+        assert_eq!(
+            policy.next_delay(&status_error(429, retry_after), 1),
+            retry_after
+        );
+
+        // out of retries
+        assert_eq!(policy.next_delay(&status_error(503, None), 3), None);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(120)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+    }
+}
@@ -1,13 +1,36 @@
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use std::path::PathBuf;
+
+use url::Url;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum DownloadState {
-    /// The download has not started
-    NotStarted,
     /// the given number of files are being downloaded
     DownloadStart(usize),
-    /// A file was downloaded that is the provided number of bytes large
-    FileDownload(u64),
-    /// An error ocurred when downloading a file
-    DownloadError,
+    /// A file started downloading to `out_path`
+    FileStart { url: Url, out_path: PathBuf },
+    /// The provided number of bytes of a file were downloaded
+    FileProgress { url: Url, bytes: u64 },
+    /// An attempt to download a file failed.
+    ///
+    /// `discarded_bytes` were reported as progress during the failed attempt
+    /// and will be downloaded again if the file is retried.
+    FileAttemptFailed {
+        url: Url,
+        error: String,
+        discarded_bytes: u64,
+    },
+    /// A file was downloaded to `out_path` that is `size` bytes large
+    FileDownload {
+        url: Url,
+        out_path: PathBuf,
+        size: u64,
+    },
+    /// An error ocurred when downloading a file, and it will not be retried
+    DownloadError {
+        url: Url,
+        out_path: PathBuf,
+        error: String,
+    },
     /// The download process completed
     Finish,
 }
@@ -1,3 +1,7 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use starview_common::enums::MirrorLayout;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -20,8 +24,107 @@ pub enum Error {
     #[error("error when parsing string as url: {0}")]
     UrlParse(#[from] url::ParseError),
 
+    #[error("serde JSON error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+
     #[error("provided path '{0}' is not a directory")]
     NotDirectory(String),
+
+    #[error("checksum mismatch for '{url}': expected sha256 {expected}, got {actual}")]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("size mismatch for '{url}': expected {expected} bytes, got {actual}")]
+    SizeMismatch {
+        url: String,
+        expected: u64,
+        actual: u64,
+    },
+
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("error when joining threads: {0}")]
+    TokioJoin(#[from] tokio::task::JoinError),
+
+    #[error("asset version '{0}' is not in the fetch cache")]
+    UnknownAssetVersion(String),
+
+    #[error("the fetch cache does not contain any asset versions")]
+    NoAssetVersion,
+
+    #[error("archive '{0}' has not been downloaded")]
+    MissingArchive(String),
+
+    #[error(
+        "assets were downloaded with the {} layout and can not be downloaded with the {} layout",
+        .mirror.name(),
+        .requested.name()
+    )]
+    LayoutMismatch {
+        mirror: MirrorLayout,
+        requested: MirrorLayout,
+    },
+
+    #[error("csv error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("invalid glob pattern: {0}")]
+    Glob(#[from] globset::Error),
+
+    #[error("invalid regex pattern: {0}")]
+    Regex(#[from] regex::Error),
+
+    #[error("invalid files list row on line {line}: {reason}")]
+    InvalidFilesListRow { line: u64, reason: String },
+
+    #[error("files list '{0}' has not been downloaded")]
+    MissingFilesList(String),
+
+    #[error("files list '{url}' could not be downloaded: {error}")]
+    FilesListDownload { url: String, error: Box<Error> },
+
+    #[error("server responded with status {status} for '{url}'")]
+    HttpStatus {
+        url: String,
+        status: u16,
+        /// How long the server asked to wait before trying again
+        retry_after: Option<Duration>,
+    },
+}
+
+impl Error {
+    /// Returns true if an operation that failed with this error could succeed if it is tried again.
+    ///
+    /// Network errors, server errors, timeouts and corrupted downloads are transient.
+    /// Client errors like a missing file or a rejected request are not.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::HttpStatus { status, .. } => StatusCode::from_u16(*status)
+                .map(|status| {
+                    status.is_server_error()
+                        || status == StatusCode::REQUEST_TIMEOUT
+                        || status == StatusCode::TOO_MANY_REQUESTS
+                })
+                .unwrap_or(false),
+            Error::Reqwest(err) => {
+                err.is_timeout() || err.is_connect() || err.is_request() || err.is_body()
+            }
+            Error::ChecksumMismatch { .. } | Error::SizeMismatch { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns how long the server asked to wait before this request is retried
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::HttpStatus { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -0,0 +1,365 @@
+use std::{
+    fs::{File, create_dir_all},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use starview_common::enums::DeviceType;
+use tokio::{sync::mpsc, task::spawn_blocking};
+use zip::ZipArchive;
+
+use crate::{Error, cache::models::FetchCache, extract::state::ExtractState, mirror::MirrorPaths};
+
+/// Interface for extracting downloaded asset archives into asset trees
+pub struct Extractor {
+    state_sender: mpsc::UnboundedSender<ExtractState>,
+    /// Every archive to extract, with the asset tree that it is extracted into
+    archives: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Extractor {
+    /// Creates a new Extractor that extracts `archives` to `out_path` in order.
+    ///
+    /// Files in later archives replace files with the same path in earlier archives.
+    ///
+    /// Every state update is sent to the returned receiver.
+    pub fn new(
+        archives: Vec<PathBuf>,
+        out_path: impl AsRef<Path>,
+    ) -> (Self, mpsc::UnboundedReceiver<ExtractState>) {
+        let out_path = out_path.as_ref();
+        Self::with_trees(
+            archives
+                .into_iter()
+                .map(|archive_path| (archive_path, out_path.to_path_buf()))
+                .collect(),
+        )
+    }
+
+    fn with_trees(
+        archives: Vec<(PathBuf, PathBuf)>,
+    ) -> (Self, mpsc::UnboundedReceiver<ExtractState>) {
+        let (state_sender, recv) = mpsc::unbounded_channel();
+
+        (
+            Self {
+                state_sender,
+                archives,
+            },
+            recv,
+        )
+    }
+
+    /// Creates a new Extractor that builds the asset tree of `asset_version`
+    /// from the archives downloaded to `mirror_path`.
+    ///
+    /// The full archives are extracted first, then the diffs leading to `asset_version`
+    /// are applied in the order that they were downloaded, see [`FetchCache::mirror_archives`].
+    ///
+    /// If `asset_version` was fetched for more than one device, the archives of each device
+    /// are extracted into their own asset tree in `out_path`, named after the device.
+    pub fn for_version(
+        cache: &FetchCache,
+        asset_version: &str,
+        mirror_path: impl AsRef<Path>,
+        out_path: impl AsRef<Path>,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<ExtractState>), Error> {
+        let cached_version = cache
+            .versions
+            .get(asset_version)
+            .ok_or_else(|| Error::UnknownAssetVersion(asset_version.into()))?;
+        let mirror = MirrorPaths::for_cache(cache, mirror_path);
+        let out_path = out_path.as_ref();
+
+        // devices have different archives for the same files, so they can not share a tree
+        let device_types: Vec<DeviceType> = cached_version
+            .device_asset_paths
+            .iter()
+            .filter_map(|asset_paths| asset_paths.device_type)
+            .collect();
+        let trees: Vec<(Option<DeviceType>, PathBuf)> = if device_types.len() > 1 {
+            device_types
+                .into_iter()
+                .map(|device_type| (Some(device_type), out_path.join(device_type.name())))
+                .collect()
+        } else {
+            vec![(None, out_path.to_path_buf())]
+        };
+
+        let mut archives: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for (device_type, tree_path) in trees {
+            let mirror_archives = cache
+                .mirror_archives(asset_version, device_type)
+                .unwrap_or_default();
+            for archive in &mirror_archives {
+                let archive_paths = mirror.archive_paths(archive)?;
+                let Some(archive_path) = archive_paths.iter().find(|path| path.is_file()) else {
+                    return Err(Error::MissingArchive(
+                        archive_paths[0].to_string_lossy().to_string(),
+                    ));
+                };
+                archives.push((archive_path.clone(), tree_path.clone()));
+            }
+        }
+
+        Ok(Self::with_trees(archives))
+    }
+
+    /// Extracts every archive given to this Extractor.
+    ///
+    /// Returns the number of files that were written
+    pub async fn extract(self) -> Result<usize, Error> {
+        let _ = self
+            .state_sender
+            .send(ExtractState::ExtractStart(self.archives.len()));
+
+        let mut extracted_files: usize = 0;
+        for (archive_path, out_path) in self.archives {
+            // archives are extracted one at a time, as later archives overwrite earlier ones
+            let state_sender = self.state_sender.clone();
+            extracted_files +=
+                spawn_blocking(move || extract_archive(&archive_path, &out_path, &state_sender))
+                    .await??;
+        }
+
+        let _ = self.state_sender.send(ExtractState::Finish);
+        Ok(extracted_files)
+    }
+}
+
+/// Extracts the zip archive at `archive_path` into `out_path`, replacing existing files.
+///
+/// Entries with paths that would be written outside of `out_path` are skipped.
+///
+/// Returns the number of files that were written
+fn extract_archive(
+    archive_path: &Path,
+    out_path: &Path,
+    state_sender: &mpsc::UnboundedSender<ExtractState>,
+) -> Result<usize, Error> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(archive_path)?))?;
+    let _ = state_sender.send(ExtractState::ArchiveStart {
+        path: archive_path.to_path_buf(),
+        entries: zip.len(),
+    });
+
+    let mut files: usize = 0;
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index)?;
+        let Some(entry_path) = entry.enclosed_name() else {
+            continue;
+        };
+
+        let file_path = out_path.join(&entry_path);
+        if entry.is_dir() {
+            create_dir_all(&file_path)?;
+            continue;
+        }
+
+        if let Some(parent) = file_path.parent() {
+            create_dir_all(parent)?;
+        }
+        let mut out_file = File::create(&file_path)?;
+        let size = io::copy(&mut entry, &mut out_file)?;
+        files += 1;
+
+        let _ = state_sender.send(ExtractState::FileExtract {
+            path: entry_path,
+            size,
+        });
+    }
+
+    let _ = state_sender.send(ExtractState::ArchiveExtract {
+        path: archive_path.to_path_buf(),
+        files,
+    });
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use starview_net::models::{
+        AssetPathArchive, AssetPathDiff, AssetPaths, AssetPathsFull, AssetPathsInfo,
+    };
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::{
+        cache::models::{AppliedPlan, CachedVersion},
+        fetch::DownloadPlan,
+        mirror::{Verifier, test_utils::archive},
+    };
+
+    fn write_zip(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_extract_overlays_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let full_path = dir.path().join("full.zip");
+        let diff_path = dir.path().join("diff.zip");
+        write_zip(&full_path, &[("a.txt", "full"), ("b/c.txt", "full")]);
+        write_zip(&diff_path, &[("a.txt", "diff"), ("../escape.txt", "diff")]);
+
+        let out_path = dir.path().join("out");
+        let (extractor, _) = Extractor::new(vec![full_path, diff_path], &out_path);
+        assert_eq!(extractor.extract().await.unwrap(), 3);
+
+        assert_eq!(
+            std::fs::read_to_string(out_path.join("a.txt")).unwrap(),
+            "diff"
+        );
+        assert_eq!(
+            std::fs::read_to_string(out_path.join("b/c.txt")).unwrap(),
+            "full"
+        );
+        assert!(!dir.path().join("escape.txt").exists());
+    }
+
+    /// Writes a zip of `files` to `name` in the mirror at `mirror_path`, returning its archive
+    fn mirror_zip(mirror_path: &Path, name: &str, files: &[(&str, &str)]) -> AssetPathArchive {
+        let path = mirror_path.join(name);
+        write_zip(&path, files);
+        archive(name, std::fs::read(path).unwrap())
+    }
+
+    fn asset_paths(version: &str, full: AssetPathsFull, diff: Vec<AssetPathDiff>) -> AssetPaths {
+        AssetPaths {
+            info: AssetPathsInfo {
+                client_asset_version: version.into(),
+                target_asset_version: version.into(),
+                eventual_target_asset_version: version.into(),
+                is_initial: false,
+                latest_maj_first_version: full.version.clone(),
+            },
+            full,
+            diff,
+            asset_version_hash: String::new(),
+            device_type: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_for_version_after_upgrade() {
+        let mirror_dir = tempfile::tempdir().unwrap();
+        let out_dir = tempfile::tempdir().unwrap();
+        let mirror_path = mirror_dir.path();
+        let full_1 = mirror_zip(mirror_path, "full-1.zip", &[("a.txt", "1"), ("b.txt", "1")]);
+        let diff_2 = mirror_zip(mirror_path, "1-2.zip", &[("b.txt", "2")]);
+        let diff_3 = mirror_zip(mirror_path, "2-3.zip", &[("a.txt", "3")]);
+        // never downloaded, the mirror was upgraded with diffs instead
+        let full_2 = archive("full-2.zip", "full 2");
+
+        let paths_1 = asset_paths(
+            "1",
+            AssetPathsFull {
+                version: "1".into(),
+                archive: vec![full_1],
+            },
+            Vec::new(),
+        );
+        let paths_3 = asset_paths(
+            "3",
+            AssetPathsFull {
+                version: "2".into(),
+                archive: vec![full_2],
+            },
+            vec![
+                AssetPathDiff {
+                    version: "2".into(),
+                    original_version: "1".into(),
+                    archive: vec![diff_2],
+                },
+                AssetPathDiff {
+                    version: "3".into(),
+                    original_version: "2".into(),
+                    archive: vec![diff_3],
+                },
+            ],
+        );
+
+        // the plans that downloading 1 and then upgrading to 3 apply
+        let plan_1 = DownloadPlan::new(&paths_1, None);
+        let plan_3 = DownloadPlan::new(&paths_3, Some("1"));
+        assert_eq!(plan_3.diff_versions, vec!["2", "3"]);
+
+        let mut cache = FetchCache::new("udid".into(), DeviceType::All);
+        for (version, paths, plan) in [("1", paths_1, plan_1), ("3", paths_3, plan_3)] {
+            let mut cached_version = CachedVersion::new(DeviceType::All, Vec::new(), paths);
+            cached_version.applied_plan = Some(AppliedPlan::from(&plan));
+            cache.versions.insert(version.into(), cached_version);
+        }
+
+        let (extractor, _) =
+            Extractor::for_version(&cache, "3", mirror_path, out_dir.path()).unwrap();
+        assert_eq!(extractor.extract().await.unwrap(), 4);
+        for (name, contents) in [("a.txt", "3"), ("b.txt", "2")] {
+            assert_eq!(
+                std::fs::read_to_string(out_dir.path().join(name)).unwrap(),
+                contents
+            );
+        }
+
+        let (verifier, _) = Verifier::for_cache(&cache, Some("3"), mirror_path, 2).unwrap();
+        let report = verifier.verify().await.unwrap();
+        assert_eq!(report.valid, 3);
+        assert!(report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_for_version_per_device() {
+        let mirror_dir = tempfile::tempdir().unwrap();
+        let out_dir = tempfile::tempdir().unwrap();
+        let mirror_path = mirror_dir.path();
+        let android = mirror_zip(mirror_path, "android.zip", &[("a.txt", "android")]);
+        let ios = mirror_zip(mirror_path, "ios.zip", &[("a.txt", "ios")]);
+
+        let device_asset_paths: Vec<AssetPaths> =
+            [(DeviceType::Android, android), (DeviceType::Ios, ios)]
+                .into_iter()
+                .map(|(device_type, archive)| AssetPaths {
+                    device_type: Some(device_type),
+                    ..asset_paths(
+                        "1",
+                        AssetPathsFull {
+                            version: "1".into(),
+                            archive: vec![archive],
+                        },
+                        Vec::new(),
+                    )
+                })
+                .collect();
+        let mut merged = device_asset_paths[0].clone();
+        merged
+            .full
+            .archive
+            .extend(device_asset_paths[1].full.archive.clone());
+        merged.device_type = None;
+
+        let mut cache = FetchCache::new("udid".into(), DeviceType::All);
+        cache.versions.insert(
+            "1".into(),
+            CachedVersion::new(DeviceType::All, Vec::new(), merged)
+                .device_asset_paths(device_asset_paths),
+        );
+
+        let (extractor, _) =
+            Extractor::for_version(&cache, "1", mirror_path, out_dir.path()).unwrap();
+        assert_eq!(extractor.extract().await.unwrap(), 2);
+        for device_type in [DeviceType::Android, DeviceType::Ios] {
+            assert_eq!(
+                std::fs::read_to_string(out_dir.path().join(device_type.name()).join("a.txt"))
+                    .unwrap(),
+                device_type.name()
+            );
+        }
+    }
+}
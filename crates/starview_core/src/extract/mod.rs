@@ -0,0 +1,5 @@
+mod extractor;
+
+pub mod state;
+
+pub use extractor::Extractor;
@@ -0,0 +1,16 @@
+use std::path::PathBuf;
+
+/// The state of an [`crate::extract::Extractor`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExtractState {
+    /// The provided number of archives will be extracted
+    ExtractStart(usize),
+    /// An archive containing the provided number of entries has started extracting
+    ArchiveStart { path: PathBuf, entries: usize },
+    /// A file was written to the asset tree
+    FileExtract { path: PathBuf, size: u64 },
+    /// An archive has been fully extracted
+    ArchiveExtract { path: PathBuf, files: usize },
+    /// Every archive has been extracted
+    Finish,
+}
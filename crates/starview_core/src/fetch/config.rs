@@ -1,15 +1,39 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
-use starview_common::enums::DeviceType;
+use starview_common::enums::{DeviceType, MirrorLayout};
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
-const DEFAULT_CACHE_PATH: &str = "starview.cache";
+use crate::{cache::DEFAULT_CACHE_PATH, download::DownloadConfig};
+
+const DEFAULT_CHECKPOINT_FILES: usize = 25;
+const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
 
 /// Configuration for [`crate::fetch::Fetcher`]
 pub struct FetchConfig {
     pub cache_path: PathBuf,
     pub device_type: Option<DeviceType>,
     pub api_host: Option<Url>,
+    /// The maximum number of bytes per second that will be downloaded
+    pub rate_limit: Option<u64>,
+    /// When cancelled, downloads stop after the files that are in progress finish
+    pub cancellation_token: CancellationToken,
+    /// The cache is written after this many assets are downloaded
+    pub checkpoint_files: usize,
+    /// The cache is written when this much time has passed since it was last written
+    /// and at least one asset was downloaded
+    pub checkpoint_interval: Duration,
+    /// The maximum number of times that a failed download is retried
+    pub retry_count: usize,
+    /// In milliseconds, how long to wait before the first retry of a download
+    pub retry_delay: u64,
+    /// How long to wait for a connection to the server before a download attempt fails
+    pub connect_timeout: Option<Duration>,
+    /// How long to wait for data from the server before a download attempt fails
+    pub read_timeout: Option<Duration>,
+    /// How archives are arranged in the directory of downloaded assets,
+    /// the layout recorded in the cache is used if this is None
+    pub layout: Option<MirrorLayout>,
 }
 
 impl FetchConfig {
@@ -18,10 +42,67 @@ impl FetchConfig {
         device_type: Option<DeviceType>,
         api_host: Option<Url>,
     ) -> Self {
+        let download_defaults = DownloadConfig::default();
         Self {
             cache_path: PathBuf::from(cache_path.unwrap_or(DEFAULT_CACHE_PATH.into())),
             device_type,
             api_host,
+            rate_limit: None,
+            cancellation_token: CancellationToken::new(),
+            checkpoint_files: DEFAULT_CHECKPOINT_FILES,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            retry_count: download_defaults.retry_count,
+            retry_delay: download_defaults.retry_delay,
+            connect_timeout: download_defaults.connect_timeout,
+            read_timeout: download_defaults.read_timeout,
+            layout: None,
         }
     }
+
+    /// Limits the download speed to `bytes_per_sec` bytes per second
+    pub fn rate_limit(mut self, bytes_per_sec: Option<u64>) -> Self {
+        self.rate_limit = bytes_per_sec;
+        self
+    }
+
+    /// Sets the token that stops downloads when it is cancelled
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Sets how often the cache is written while assets are downloading.
+    ///
+    /// The cache is written every `files` downloaded assets,
+    /// or once `interval` has passed since it was last written.
+    pub fn checkpoint(mut self, files: usize, interval: Duration) -> Self {
+        self.checkpoint_files = files;
+        self.checkpoint_interval = interval;
+        self
+    }
+
+    /// Sets how many times a failed download is retried,
+    /// and the delay in milliseconds before the first retry
+    pub fn retry(mut self, retry_count: usize, retry_delay: u64) -> Self {
+        self.retry_count = retry_count;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Sets how long a download attempt waits for a connection and for data before it fails
+    pub fn timeouts(
+        mut self,
+        connect_timeout: Option<Duration>,
+        read_timeout: Option<Duration>,
+    ) -> Self {
+        self.connect_timeout = connect_timeout;
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    /// Sets how archives are arranged in the directory of downloaded assets
+    pub fn layout(mut self, layout: Option<MirrorLayout>) -> Self {
+        self.layout = layout;
+        self
+    }
 }
@@ -1,45 +1,75 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     fs::create_dir_all,
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
 
-use starview_common::{OptionalBuilder, enums::AssetSize};
+use starview_common::{
+    OptionalBuilder,
+    enums::{AssetSize, MirrorLayout},
+};
 use starview_net::{
     client::WafuriAPIClient,
     models::{AssetPathArchive, AssetPaths, AssetVersionInfo},
 };
-use tokio::{join, sync::watch, try_join};
+use tokio::{join, sync::mpsc, time::Instant, try_join};
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
 use crate::{
     Error,
-    cache::models::FetchCache,
-    download::{DownloadConfig, Downloader, state::DownloadState},
+    cache::models::{AppliedPlan, CachedVersion, FailedDownload, FetchCache},
+    download::{
+        DownloadConfig, DownloadConfigBuilder, DownloadFile, Downloader, RateLimiter,
+        state::DownloadState,
+    },
     error::FetchCacheError,
     fetch::{
-        FetchConfig,
-        state::{DownloadAssetsState, DownloadFilesListState, FetchAssetInfoState, FetchState},
+        DownloadPlan, DownloadSummary, FetchConfig,
+        state::{
+            DownloadAssetsState, DownloadFilesListState, FetchAssetInfoState, FetchState,
+            ImportAssetsState, WatchState,
+        },
     },
+    mirror::{FILES_LIST_URL_STRIP_PREFIX, ImportReport, Importer, MirrorPaths},
 };
 
-const DOWNLOAD_URL_STRIP_PREFIX: &str = "/patch/gf/upload_assets";
-const DOWNLOAD_FILES_LIST_URL_STRIP_PREFIX: &str = "/patch/gf/upload_assets/entities";
-
 /// Interface for communicating with the game's API
 pub struct Fetcher {
-    state_sender: watch::Sender<FetchState>,
+    state_sender: mpsc::UnboundedSender<FetchState>,
     client: WafuriAPIClient,
     cache_path: PathBuf,
     cache: FetchCache,
+    rate_limiter: RateLimiter,
+    cancellation_token: CancellationToken,
+    checkpoint_files: usize,
+    checkpoint_interval: Duration,
+    retry_count: usize,
+    retry_delay: u64,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    layout: Option<MirrorLayout>,
 }
 
 impl Fetcher {
     /// Initializes a new Fetcher with the provided config.
-    pub async fn new(config: FetchConfig) -> Result<(Self, watch::Receiver<FetchState>), Error> {
-        // get cache
-        let cache = FetchCache::from_path(&config.cache_path).await.ok();
+    ///
+    /// A new cache is created if there is no file at the config's cache path,
+    /// any other error when reading the cache is returned.
+    ///
+    /// Every state update is sent to the returned receiver.
+    pub async fn new(
+        config: FetchConfig,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<FetchState>), Error> {
+        // get cache, only starting a new one if there is none,
+        // so a cache that can not be read is never replaced along with its UDID
+        let cache = match FetchCache::from_path(&config.cache_path).await {
+            Ok(cache) => Some(cache),
+            Err(FetchCacheError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
 
         // build client
         let client = if let Some(cache) = &cache {
@@ -58,7 +88,7 @@ impl Fetcher {
             .build()?;
         client.signup().await?;
 
-        let (state_sender, recv) = watch::channel(FetchState::None);
+        let (state_sender, recv) = mpsc::unbounded_channel();
 
         Ok((
             Self {
@@ -66,55 +96,129 @@ impl Fetcher {
                 cache: cache.unwrap_or(FetchCache::new(client.uuid.clone(), client.device_type)),
                 cache_path: config.cache_path,
                 client,
+                rate_limiter: RateLimiter::new(config.rate_limit),
+                cancellation_token: config.cancellation_token,
+                checkpoint_files: config.checkpoint_files,
+                checkpoint_interval: config.checkpoint_interval,
+                retry_count: config.retry_count,
+                retry_delay: config.retry_delay,
+                connect_timeout: config.connect_timeout,
+                read_timeout: config.read_timeout,
+                layout: config.layout,
             },
             recv,
         ))
     }
 
+    /// Returns a handle to the rate limiter shared by all of this Fetcher's downloads.
+    ///
+    /// The handle can be used to change the download speed limit while files are downloading.
+    pub fn rate_limiter(&self) -> RateLimiter {
+        self.rate_limiter.clone()
+    }
+
+    /// Returns the fetch cache, including every asset version fetched so far
+    pub fn cache(&self) -> &FetchCache {
+        &self.cache
+    }
+
+    /// Creates a DownloadConfigBuilder with the download options that every download shares
+    fn download_config(&self) -> DownloadConfigBuilder {
+        DownloadConfig::builder()
+            .retry_count(self.retry_count)
+            .retry_delay(self.retry_delay)
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+            .rate_limiter(self.rate_limiter.clone())
+            .cancellation_token(self.cancellation_token.clone())
+    }
+
+    /// Returns the paths of archives in the asset directory at `out_path`,
+    /// recording this Fetcher's layout in the cache.
+    ///
+    /// Fails if assets were already downloaded with a different layout.
+    fn mirror_paths(&mut self, out_path: impl AsRef<Path>) -> Result<MirrorPaths, Error> {
+        if let Some(layout) = self.layout
+            && layout != self.cache.mirror_layout
+        {
+            if !self.cache.downloaded_asset_hashes.is_empty() {
+                return Err(Error::LayoutMismatch {
+                    mirror: self.cache.mirror_layout,
+                    requested: layout,
+                });
+            }
+            self.cache.mirror_layout = layout;
+        }
+        Ok(MirrorPaths::for_cache(&self.cache, out_path))
+    }
+
+    /// Sends a state update to the receiver returned by [`Fetcher::new`].
+    ///
+    /// The receiver may have been dropped if the caller does not need state updates,
+    /// so send errors are ignored.
+    fn send_state(&self, state: FetchState) {
+        let _ = self.state_sender.send(state);
+    }
+
     /// Writes the fetch cache to `self.cache_path`
     async fn write_cache(&self) -> Result<(), FetchCacheError> {
         self.cache.write(&self.cache_path).await
     }
 
     /// Fetches version info and asset paths from the game server for the provided `asset_version`.
+    ///
+    /// Asset info is stored in the cache alongside every other version that was fetched,
+    /// so it is only requested from the game server once per version and device type.
+    /// The asset paths of each device are kept alongside the merged asset paths.
     pub async fn get_asset_info(
         &mut self,
         asset_version: &str,
     ) -> Result<(Vec<AssetVersionInfo>, AssetPaths), Error> {
-        if let (Some(asset_paths), version_info) =
-            (&self.cache.asset_paths, &self.cache.version_info)
+        // skip updating if the cache contains this version for the client's device type,
+        // versions cached before the asset paths of each device were kept are fetched again
+        if let Some(cached_version) = self.cache.versions.get(asset_version)
+            && cached_version.device_type == self.client.device_type
+            && !cached_version.device_asset_paths.is_empty()
         {
-            // skip updating if:
-            // - cache contains both asset_paths and version info
-            // - and device type is the same as the client's device type
-            if (asset_paths.info.client_asset_version == asset_version)
-                && (self.cache.device_type == self.client.device_type)
-            {
-                self.state_sender
-                    .send_replace(FetchState::AssetInfo(FetchAssetInfoState::Finish));
-                return Ok((version_info.clone(), asset_paths.clone()));
-            }
+            self.send_state(FetchState::AssetInfo(FetchAssetInfoState::Finish));
+            return Ok((
+                cached_version.version_info.clone(),
+                cached_version.asset_paths.clone(),
+            ));
         }
 
-        // update cache by fetching the most recent asset paths & asset version info
-        self.state_sender
-            .send_replace(FetchState::AssetInfo(FetchAssetInfoState::GetAssetInfo));
-        let asset_paths_future = self.client.get_asset_path(asset_version, AssetSize::Full);
+        // update cache by fetching the asset paths & asset version info for this version
+        self.send_state(FetchState::AssetInfo(FetchAssetInfoState::GetAssetInfo));
+        let asset_paths_future = self
+            .client
+            .get_asset_paths_per_device(asset_version, AssetSize::Full);
         let asset_version_info_future = self.client.get_asset_version_info(asset_version);
 
-        if let (Some(mut asset_paths), asset_version_info) =
-            try_join!(asset_paths_future, asset_version_info_future)?
-        {
+        let (mut device_asset_paths, asset_version_info) =
+            try_join!(asset_paths_future, asset_version_info_future)?;
+        for asset_paths in &mut device_asset_paths {
             asset_paths.info.client_asset_version = asset_paths.info.target_asset_version.clone();
+        }
 
+        if let Some(asset_paths) = device_asset_paths
+            .iter()
+            .cloned()
+            .reduce(AssetPaths::extend)
+        {
             // update cache
-            self.cache.asset_paths = Some(asset_paths.clone());
-            self.cache.version_info = asset_version_info.clone();
+            self.cache.versions.insert(
+                asset_version.into(),
+                CachedVersion::new(
+                    self.client.device_type,
+                    asset_version_info.clone(),
+                    asset_paths.clone(),
+                )
+                .device_asset_paths(device_asset_paths),
+            );
             self.cache.device_type = self.client.device_type;
             self.write_cache().await?;
 
-            self.state_sender
-                .send_replace(FetchState::AssetInfo(FetchAssetInfoState::Finish));
+            self.send_state(FetchState::AssetInfo(FetchAssetInfoState::Finish));
 
             Ok((asset_version_info, asset_paths))
         } else {
@@ -124,165 +228,470 @@ impl Fetcher {
         }
     }
 
-    /// Fetches the latest version info and asset paths from the game server.
-    pub async fn get_latest_asset_info(
-        &mut self,
-    ) -> Result<(Vec<AssetVersionInfo>, AssetPaths), Error> {
-        self.state_sender
-            .send_replace(FetchState::AssetInfo(FetchAssetInfoState::GetAssetVersion));
-        let available_asset_version = {
-            let user_data =
+    /// Requests the asset version that the game server currently serves.
+    ///
+    /// If the request fails, the client logs in again in case its session expired,
+    /// and the request is retried once.
+    pub async fn get_available_asset_version(&mut self) -> Result<String, Error> {
+        let user_data = match self.client.load().await {
+            Ok(Some(user_data)) => user_data,
+            _ => {
+                self.send_state(FetchState::Watch(WatchState::Relogin));
+                self.client.logout();
+                self.client.signup().await?;
                 self.client
                     .load()
                     .await?
                     .ok_or(starview_net::Error::InvalidRequest(
                         "could not load player data".into(),
-                    ))?;
-
-            user_data.available_asset_version
+                    ))?
+            }
         };
 
+        Ok(user_data.available_asset_version)
+    }
+
+    /// Fetches the latest version info and asset paths from the game server.
+    pub async fn get_latest_asset_info(
+        &mut self,
+    ) -> Result<(Vec<AssetVersionInfo>, AssetPaths), Error> {
+        self.send_state(FetchState::AssetInfo(FetchAssetInfoState::GetAssetVersion));
+        let available_asset_version = self.get_available_asset_version().await?;
+        self.cache.latest_asset_version = Some(available_asset_version.clone());
+
         self.get_asset_info(&available_asset_version).await
     }
 
-    /// Inserts `url_str` into `url_hash_map` and `to_download_urls` if
-    /// `hash` is not inside the provided `downloaded_asset_hashes` HashSet.
+    /// Polls the game server every `interval` until it serves an asset version
+    /// other than `current_version`, then returns the new version.
+    /// If `current_version` is None, the first version the server serves is returned.
     ///
-    /// Inserts `hash` into `new_downloaded_asset_hashes`
-    /// if it was already in `downloaded_asset_hashes`
+    /// Failed polls are not fatal, the delay before the next poll doubles after every
+    /// consecutive failure up to `max_backoff`.
     ///
-    /// Returns the number of bytes that should be downloaded
-    fn insert_url_if_not_downloaded(
-        archive: AssetPathArchive,
-        downloaded_asset_hashes: &HashSet<String>,
-        to_download_urls: &mut Vec<Url>,
-        url_hash_map: &mut HashMap<Url, String>,
-        new_downloaded_asset_hashes: &mut HashSet<String>,
-    ) -> Result<u64, url::ParseError> {
-        let hash = archive.sha256;
-        if !downloaded_asset_hashes.contains(&hash) {
-            let url = Url::from_str(&archive.location)?;
-            url_hash_map.insert(url.clone(), hash);
-            to_download_urls.push(url);
-            Ok(archive.size)
-        } else {
-            new_downloaded_asset_hashes.insert(hash);
-            Ok(0)
+    /// Returns None if the fetcher's cancellation token is cancelled while waiting.
+    pub async fn wait_for_new_asset_version(
+        &mut self,
+        current_version: Option<&str>,
+        interval: Duration,
+        max_backoff: Duration,
+    ) -> Option<String> {
+        let mut failures: u32 = 0;
+        loop {
+            self.send_state(FetchState::Watch(WatchState::Poll));
+            let delay = match self.get_available_asset_version().await {
+                Ok(asset_version) if current_version != Some(asset_version.as_str()) => {
+                    self.send_state(FetchState::Watch(WatchState::NewVersion(
+                        asset_version.clone(),
+                    )));
+                    return Some(asset_version);
+                }
+                Ok(asset_version) => {
+                    failures = 0;
+                    self.send_state(FetchState::Watch(WatchState::Unchanged(asset_version)));
+                    interval
+                }
+                Err(err) => {
+                    failures += 1;
+                    let retry_in = poll_backoff(interval, max_backoff, failures);
+                    self.send_state(FetchState::Watch(WatchState::PollFailed {
+                        error: err.to_string(),
+                        retry_in,
+                    }));
+                    retry_in
+                }
+            };
+
+            tokio::select! {
+                _ = self.cancellation_token.cancelled() => return None,
+                _ = tokio::time::sleep(delay) => {}
+            }
         }
     }
 
-    /// Watches a DownloadState receiver for any changes,
-    /// bridging them into a FetchState::DownloadAssets state update
-    /// for this Fetcher.
+    /// Fetches the version info and asset paths for `asset_version`,
+    /// or for the latest version if `asset_version` is None.
+    pub async fn get_asset_info_or_latest(
+        &mut self,
+        asset_version: Option<&str>,
+    ) -> Result<(Vec<AssetVersionInfo>, AssetPaths), Error> {
+        match asset_version {
+            Some(asset_version) => self.get_asset_info(asset_version).await,
+            None => self.get_latest_asset_info().await,
+        }
+    }
+
+    /// Receives every DownloadState update from a Downloader,
+    /// bridging them into FetchState updates for this Fetcher
+    /// using `wrap_state`.
     async fn bridge_download_state(
-        mut download_recv: watch::Receiver<DownloadState>,
-        state_sender: watch::Sender<FetchState>,
+        mut download_recv: mpsc::UnboundedReceiver<DownloadState>,
+        state_sender: mpsc::UnboundedSender<FetchState>,
+        wrap_state: fn(DownloadState) -> FetchState,
     ) {
-        while download_recv.changed().await.is_ok() {
-            let download_state = *download_recv.borrow_and_update();
-            state_sender.send_replace(FetchState::DownloadAssets(DownloadAssetsState::Download(
-                download_state,
-            )));
-            // break if the download state is Finish
-            if download_state == DownloadState::Finish {
+        while let Some(download_state) = download_recv.recv().await {
+            let is_finish = download_state == DownloadState::Finish;
+            let _ = state_sender.send(wrap_state(download_state));
+            if is_finish {
                 break;
             }
         }
     }
 
-    /// Downloads the latest assets from the game server to the provided directory `out_path`
-    pub async fn download_assets(
+    /// Receives every DownloadState update from an asset Downloader,
+    /// bridging them into FetchState updates for this Fetcher.
+    ///
+    /// When an archive in `url_archive_map` finishes downloading, its hash is inserted into the cache.
+    /// The cache is written every `checkpoint_files` archives or `checkpoint_interval`.
+    async fn checkpoint_download_state(
         &mut self,
-        out_path: impl AsRef<Path>,
-        concurrency: usize,
-    ) -> Result<(), Error> {
-        validate_dir(&out_path)?;
+        mut download_recv: mpsc::UnboundedReceiver<DownloadState>,
+        url_archive_map: &HashMap<Url, AssetPathArchive>,
+    ) {
+        let mut last_checkpoint = Instant::now();
+        let mut files_since_checkpoint: usize = 0;
 
-        // extract info from FetchCache or get it from the game servers
-        self.state_sender.send_replace(FetchState::DownloadAssets(
-            DownloadAssetsState::FetchAssetInfo,
-        ));
-        let (_, asset_paths) = self.get_latest_asset_info().await?;
-        let downloaded_asset_hashes = &self.cache.downloaded_asset_hashes;
+        while let Some(download_state) = download_recv.recv().await {
+            if let DownloadState::FileDownload { url, .. } = &download_state
+                && let Some(archive) = url_archive_map.get(url)
+            {
+                self.cache
+                    .downloaded_asset_hashes
+                    .insert(archive.sha256.clone());
+                self.cache.failed_downloads.remove(&archive.location);
+                files_since_checkpoint += 1;
+            }
 
-        // generate hashmap of urls to download
-        let mut to_download_urls: Vec<Url> = Vec::new();
-        let mut url_hash_map: HashMap<Url, String> = HashMap::new();
-        let mut new_downloaded_asset_hashes: HashSet<String> = HashSet::new();
-        let mut total_bytes: u64 = 0;
+            if files_since_checkpoint > 0
+                && (files_since_checkpoint >= self.checkpoint_files
+                    || last_checkpoint.elapsed() >= self.checkpoint_interval)
+            {
+                // a failed checkpoint is not fatal,
+                // the cache is written again once the download finishes
+                let _ = self.write_cache().await;
+                last_checkpoint = Instant::now();
+                files_since_checkpoint = 0;
+            }
 
-        for archive in asset_paths.full.archive {
-            total_bytes += Self::insert_url_if_not_downloaded(
-                archive,
-                downloaded_asset_hashes,
-                &mut to_download_urls,
-                &mut url_hash_map,
-                &mut new_downloaded_asset_hashes,
-            )?;
+            let is_finish = download_state == DownloadState::Finish;
+            self.send_state(FetchState::DownloadAssets(DownloadAssetsState::Download(
+                download_state,
+            )));
+            if is_finish {
+                break;
+            }
         }
-        for diff in asset_paths.diff {
-            for archive in diff.archive {
-                total_bytes += Self::insert_url_if_not_downloaded(
-                    archive,
-                    downloaded_asset_hashes,
-                    &mut to_download_urls,
-                    &mut url_hash_map,
-                    &mut new_downloaded_asset_hashes,
-                )?;
+    }
+
+    /// Downloads `archives` to `mirror`, verifying each one against its size and sha256.
+    /// Archives that are stored at multiple paths are linked to the rest of them once downloaded.
+    ///
+    /// Downloaded archives are added to the cache's downloaded asset hashes as they finish,
+    /// and the cache is periodically written so that progress is not lost if the download is
+    /// interrupted. Failed downloads are recorded in the cache, and removed from it once they succeed.
+    ///
+    /// Returns the archives that were downloaded and a summary of the download.
+    async fn download_archives(
+        &mut self,
+        archives: Vec<AssetPathArchive>,
+        mirror: &MirrorPaths,
+        concurrency: usize,
+    ) -> Result<(Vec<AssetPathArchive>, DownloadSummary), Error> {
+        // generate the files to download, skipping duplicate urls
+        let mut url_archive_map: HashMap<Url, AssetPathArchive> = HashMap::new();
+        let mut to_download_files: Vec<DownloadFile> = Vec::new();
+        let mut total_bytes: u64 = 0;
+        for archive in archives {
+            let url = Url::from_str(&archive.location)?;
+            if url_archive_map.contains_key(&url) {
+                continue;
             }
+            total_bytes += archive.size;
+            to_download_files.push(
+                DownloadFile::new(url.clone())
+                    .sha256(archive.sha256.clone())
+                    .size(archive.size)
+                    .out_path(mirror.archive_path(&archive)?),
+            );
+            url_archive_map.insert(url, archive);
         }
 
         // send download start state with total download bytes
-        self.state_sender.send_replace(FetchState::DownloadAssets(
+        self.send_state(FetchState::DownloadAssets(
             DownloadAssetsState::DownloadStart(total_bytes),
         ));
 
         // create downloader
-        let download_config = DownloadConfig::builder()
-            .urls(to_download_urls)
-            .out_path(out_path)
-            .url_strip_prefix(DOWNLOAD_URL_STRIP_PREFIX.into())
+        let download_config = self
+            .download_config()
+            .files(to_download_files)
+            .out_path(mirror.mirror_path())
             .concurrency(concurrency)
+            .resume(true)
             .build();
-        let (downloader, recv) = Downloader::new(download_config);
+        let (downloader, recv) = Downloader::new(download_config)?;
 
-        // listen to the downloader state recv
-        // and bridge to FetchState
-        let watch_future = Self::bridge_download_state(recv, self.state_sender.clone());
+        // listen to the downloader state recv,
+        // checkpointing the cache and bridging to FetchState
+        let checkpoint_future = self.checkpoint_download_state(recv, &url_archive_map);
         let download_future = downloader.download();
 
         // join download futures
-        let (_, download_result) = join!(watch_future, download_future);
-        let (downloaded_urls, _) = download_result?;
+        let (_, download_result) = join!(checkpoint_future, download_future);
+        let (downloaded_urls, download_failures) = download_result?;
 
-        // insert downloaded urls into new downloaded asset hashes hashset
+        let mut downloaded_archives: Vec<AssetPathArchive> =
+            Vec::with_capacity(downloaded_urls.len());
         for downloaded_url in downloaded_urls {
-            if let Some(hash) = url_hash_map.remove(&downloaded_url) {
-                new_downloaded_asset_hashes.insert(hash);
+            if let Some(archive) = url_archive_map.remove(&downloaded_url) {
+                mirror.link_archive(&archive).await?;
+                self.cache.failed_downloads.remove(&archive.location);
+                downloaded_archives.push(archive);
             }
         }
 
-        // replace downloaded asset hashes in cache & write
-        self.cache.downloaded_asset_hashes = new_downloaded_asset_hashes;
+        let mut failed: Vec<FailedDownload> = Vec::with_capacity(download_failures.len());
+        for download_failure in download_failures {
+            if let Some(archive) = url_archive_map.remove(&download_failure.url) {
+                let failed_download = self
+                    .cache
+                    .failed_downloads
+                    .entry(archive.location.clone())
+                    .or_insert_with(|| FailedDownload::new(archive));
+                failed_download.record_failure(
+                    download_failure.error.to_string(),
+                    download_failure.attempts,
+                );
+                failed.push(failed_download.clone());
+            }
+        }
+
+        let summary = DownloadSummary {
+            downloaded: downloaded_archives.len(),
+            failed,
+            cancelled: self.cancellation_token.is_cancelled(),
+        };
+        Ok((downloaded_archives, summary))
+    }
+
+    /// Plans the smallest download that upgrades a mirror at `from_version` to `asset_version`,
+    /// or to the latest version if `asset_version` is None.
+    ///
+    /// If `from_version` is None, the version that the mirror was last upgraded to is used.
+    pub async fn plan_download(
+        &mut self,
+        asset_version: Option<&str>,
+        from_version: Option<&str>,
+    ) -> Result<DownloadPlan, Error> {
+        let (_, asset_paths) = self.get_asset_info_or_latest(asset_version).await?;
+        let from_version = from_version
+            .map(String::from)
+            .or_else(|| self.cache.mirror_version.clone());
+        Ok(DownloadPlan::new(&asset_paths, from_version.as_deref()))
+    }
+
+    /// Downloads the assets of `asset_version` to the provided directory `out_path`,
+    /// or the latest assets if `asset_version` is None.
+    ///
+    /// Only the archives needed to upgrade the mirror from `from_version` are downloaded,
+    /// see [`Fetcher::plan_download`].
+    ///
+    /// Archives are arranged by the Fetcher's [`MirrorLayout`]. In every layout, archives of multiple
+    /// versions can share a directory and archives that are shared between versions are only downloaded once.
+    /// Content-addressed mirrors get an index of the version's archives for each device.
+    ///
+    /// Assets that could not be downloaded are recorded in the cache
+    /// and can be retried with [`Fetcher::retry_failed_downloads`].
+    pub async fn download_assets(
+        &mut self,
+        asset_version: Option<&str>,
+        from_version: Option<&str>,
+        out_path: impl AsRef<Path>,
+        concurrency: usize,
+    ) -> Result<DownloadSummary, Error> {
+        validate_dir(&out_path)?;
+
+        // extract info from FetchCache or get it from the game servers
+        self.send_state(FetchState::DownloadAssets(
+            DownloadAssetsState::FetchAssetInfo,
+        ));
+        let plan = self.plan_download(asset_version, from_version).await?;
+        self.send_state(FetchState::DownloadAssets(DownloadAssetsState::Plan(
+            plan.clone(),
+        )));
+
+        // skip archives that have already been downloaded
+        let (already_downloaded, to_download_archives): (Vec<AssetPathArchive>, _) = plan
+            .archives
+            .iter()
+            .cloned()
+            .partition(|archive| self.cache.downloaded_asset_hashes.contains(&archive.sha256));
+
+        // archives that were downloaded for other devices still need to be in this device's tree
+        let mirror = self.mirror_paths(out_path)?;
+        for archive in &already_downloaded {
+            mirror.link_missing(archive).await?;
+        }
+        let (downloaded_archives, summary) = self
+            .download_archives(to_download_archives, &mirror, concurrency)
+            .await?;
+        self.cache.downloaded_asset_hashes.extend(
+            downloaded_archives
+                .into_iter()
+                .map(|archive| archive.sha256),
+        );
+        if summary.failed.is_empty() && !summary.cancelled {
+            // a plan from the target version itself applied nothing,
+            // so the download that built the target version is kept
+            if plan.from_version.as_ref() != Some(&plan.target_version)
+                && let Some(cached_version) = self.cache.versions.get_mut(&plan.target_version)
+            {
+                cached_version.applied_plan = Some(AppliedPlan::from(&plan));
+            }
+            if self.cache.versions.contains_key(&plan.target_version) {
+                mirror
+                    .write_indexes(&self.cache, &plan.target_version)
+                    .await?;
+            }
+            self.cache.mirror_version = Some(plan.target_version);
+        }
+
+        // forget hashes that are no longer referenced by any cached version & write
+        let referenced_asset_hashes = self.cache.referenced_asset_hashes();
+        self.cache
+            .downloaded_asset_hashes
+            .retain(|hash| referenced_asset_hashes.contains(hash));
         self.write_cache().await?;
-        self.state_sender
-            .send_replace(FetchState::DownloadAssets(DownloadAssetsState::Finish));
+        self.send_state(FetchState::DownloadAssets(DownloadAssetsState::Finish));
 
-        Ok(())
+        Ok(summary)
     }
 
-    /// Downloads file list CSVs to the provided `out_path`.
+    /// Retries every asset download that previously failed, saving them to `out_path`
+    pub async fn retry_failed_downloads(
+        &mut self,
+        out_path: impl AsRef<Path>,
+        concurrency: usize,
+    ) -> Result<DownloadSummary, Error> {
+        validate_dir(&out_path)?;
+
+        let to_download_archives: Vec<AssetPathArchive> = self
+            .cache
+            .failed_downloads
+            .values()
+            .map(|failed_download| failed_download.archive.clone())
+            .collect();
+
+        let mirror = self.mirror_paths(out_path)?;
+        let (downloaded_archives, summary) = self
+            .download_archives(to_download_archives, &mirror, concurrency)
+            .await?;
+        self.cache.downloaded_asset_hashes.extend(
+            downloaded_archives
+                .into_iter()
+                .map(|archive| archive.sha256),
+        );
+
+        self.write_cache().await?;
+        self.send_state(FetchState::DownloadAssets(DownloadAssetsState::Finish));
+
+        Ok(summary)
+    }
+
+    /// Finds the archives that build `asset_version` that already exist in `mirror_path`,
+    /// or of the latest version if `asset_version` is None, see [`FetchCache::mirror_archives`],
+    /// and records them in the cache as downloaded without downloading anything.
+    ///
+    /// If every archive needed to build `asset_version` was found,
+    /// the mirror is recorded as being upgraded to that version.
+    pub async fn import_assets(
+        &mut self,
+        asset_version: Option<&str>,
+        mirror_path: impl AsRef<Path>,
+        concurrency: usize,
+        force: bool,
+    ) -> Result<ImportReport, Error> {
+        let mirror_path = mirror_path.as_ref();
+        if !mirror_path.is_dir() {
+            return Err(Error::NotDirectory(
+                mirror_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        self.send_state(FetchState::ImportAssets(ImportAssetsState::FetchAssetInfo));
+        let (_, asset_paths) = self.get_asset_info_or_latest(asset_version).await?;
+        // the same archives that verifying the version expects
+        let target_version = asset_paths.info.target_asset_version.clone();
+        let archives = self
+            .cache
+            .mirror_archives(&target_version, None)
+            .unwrap_or_default();
+
+        // bridge import states until the importer finishes and drops its sender
+        let mirror = self.mirror_paths(mirror_path)?;
+        let (importer, mut import_recv) = Importer::new(archives, mirror, concurrency);
+        let importer = importer.force(force);
+        let state_sender = self.state_sender.clone();
+        let bridge_future = async move {
+            while let Some(import_state) = import_recv.recv().await {
+                let _ = state_sender.send(FetchState::ImportAssets(ImportAssetsState::Import(
+                    import_state,
+                )));
+            }
+        };
+        let (_, import_result) = join!(bridge_future, importer.import());
+        let report = import_result?;
+
+        for archive in &report.imported {
+            self.cache
+                .downloaded_asset_hashes
+                .insert(archive.sha256.clone());
+            self.cache.failed_downloads.remove(&archive.location);
+        }
+
+        let is_complete = self
+            .cache
+            .mirror_archives(&target_version, None)
+            .is_some_and(|archives| {
+                archives
+                    .iter()
+                    .all(|archive| self.cache.downloaded_asset_hashes.contains(&archive.sha256))
+            });
+        if is_complete {
+            self.cache.mirror_version = Some(target_version);
+        }
+
+        self.write_cache().await?;
+        self.send_state(FetchState::ImportAssets(ImportAssetsState::Finish));
+
+        Ok(report)
+    }
+
+    /// Downloads the file list CSVs of `asset_version` to the provided `out_path`,
+    /// or the latest file lists if `asset_version` is None.
+    ///
+    /// The lists are saved to a directory named after their asset version,
+    /// so lists of multiple versions can share `out_path`.
     ///
     /// A maximum of two files will be downloaded
-    /// depending on the DeviceType provided to this fetcher
-    pub async fn download_files_list(&mut self, out_path: impl AsRef<Path>) -> Result<(), Error> {
+    /// depending on the DeviceType provided to this fetcher.
+    ///
+    /// Returns an error if any files list could not be downloaded
+    pub async fn download_files_list(
+        &mut self,
+        asset_version: Option<&str>,
+        out_path: impl AsRef<Path>,
+    ) -> Result<(), Error> {
         validate_dir(&out_path)?;
 
-        self.state_sender
-            .send_replace(FetchState::DownloadFilesList(
-                DownloadFilesListState::FetchAssetInfo,
-            ));
-        let (asset_version_info, _) = self.get_latest_asset_info().await?;
+        self.send_state(FetchState::DownloadFilesList(
+            DownloadFilesListState::FetchAssetInfo,
+        ));
+        let (asset_version_info, asset_paths) =
+            self.get_asset_info_or_latest(asset_version).await?;
+        let out_path = out_path
+            .as_ref()
+            .join(&asset_paths.info.client_asset_version);
 
         let mut to_download_urls: Vec<Url> = Vec::new();
         for info in asset_version_info.iter().take(2) {
@@ -291,36 +700,51 @@ impl Fetcher {
         }
 
         // create downloader
-        self.state_sender
-            .send_replace(FetchState::DownloadFilesList(
-                DownloadFilesListState::DownloadStart(to_download_urls.len().try_into().unwrap()),
-            ));
-        let download_config = DownloadConfig::builder()
+        self.send_state(FetchState::DownloadFilesList(
+            DownloadFilesListState::DownloadStart(to_download_urls.len().try_into().unwrap()),
+        ));
+        let download_config = self
+            .download_config()
             .urls(to_download_urls)
-            .out_path(out_path)
-            .url_strip_prefix(DOWNLOAD_FILES_LIST_URL_STRIP_PREFIX.into())
+            .out_path(&out_path)
+            .url_strip_prefix(FILES_LIST_URL_STRIP_PREFIX.into())
             .concurrency(2)
             .build();
-        let (downloader, recv) = Downloader::new(download_config);
+        let (downloader, recv) = Downloader::new(download_config)?;
 
         // listen to the downloader state recv
         // and bridge to FetchState
-        let watch_future = Self::bridge_download_state(recv, self.state_sender.clone());
+        let watch_future = Self::bridge_download_state(recv, self.state_sender.clone(), |state| {
+            FetchState::DownloadFilesList(DownloadFilesListState::Download(state))
+        });
         let download_future = downloader.download();
 
         // join download futures
         let (_, download_result) = join!(watch_future, download_future);
-        download_result?;
+        let (_, failures) = download_result?;
 
-        self.state_sender
-            .send_replace(FetchState::DownloadFilesList(
-                DownloadFilesListState::Finish,
-            ));
+        self.send_state(FetchState::DownloadFilesList(
+            DownloadFilesListState::Finish,
+        ));
 
+        // callers such as changelogs rely on every files list being there
+        if let Some(failure) = failures.into_iter().next() {
+            return Err(Error::FilesListDownload {
+                url: failure.url.to_string(),
+                error: Box::new(failure.error),
+            });
+        }
         Ok(())
     }
 }
 
+/// Returns how long to wait before polling again after `failures` consecutive failed polls,
+/// doubling `interval` for every failure up to `max_backoff`
+fn poll_backoff(interval: Duration, max_backoff: Duration, failures: u32) -> Duration {
+    let multiplier = 2u32.saturating_pow(failures.min(16));
+    interval.saturating_mul(multiplier).min(max_backoff)
+}
+
 fn validate_dir(dir_path: impl AsRef<Path>) -> Result<(), Error> {
     // confirm that out_path is a directory
     let dir_path = dir_path.as_ref();
@@ -336,3 +760,48 @@ fn validate_dir(dir_path: impl AsRef<Path>) -> Result<(), Error> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_backoff() {
+        let interval = Duration::from_secs(60);
+        let max_backoff = Duration::from_secs(600);
+        assert_eq!(
+            poll_backoff(interval, max_backoff, 1),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            poll_backoff(interval, max_backoff, 3),
+            Duration::from_secs(480)
+        );
+        assert_eq!(poll_backoff(interval, max_backoff, 4), max_backoff);
+        assert_eq!(poll_backoff(interval, max_backoff, u32::MAX), max_backoff);
+    }
+
+    #[tokio::test]
+    async fn test_new_keeps_unreadable_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("starview.cache");
+        std::fs::write(&cache_path, "{ not a cache").unwrap();
+
+        // an unreachable host, the cache is read before signing up
+        let api_host = Url::parse("http://127.0.0.1:9").unwrap();
+        let config = FetchConfig::new(
+            Some(cache_path.to_string_lossy().to_string()),
+            None,
+            Some(api_host),
+        );
+        let result = Fetcher::new(config).await;
+        assert!(matches!(
+            result,
+            Err(Error::FetchCache(FetchCacheError::SerdeJson(_)))
+        ));
+        assert_eq!(
+            std::fs::read_to_string(&cache_path).unwrap(),
+            "{ not a cache"
+        );
+    }
+}
@@ -1,7 +1,11 @@
 mod config;
 mod fetcher;
+mod plan;
+mod summary;
 
 pub mod state;
 
 pub use config::FetchConfig;
 pub use fetcher::Fetcher;
+pub use plan::DownloadPlan;
+pub use summary::DownloadSummary;
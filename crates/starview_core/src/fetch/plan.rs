@@ -0,0 +1,265 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+};
+
+use starview_net::models::{AssetPathArchive, AssetPathDiff, AssetPaths};
+
+/// The archives that need to be downloaded to bring a mirror up to a target asset version
+#[derive(Clone, Debug)]
+pub struct DownloadPlan {
+    /// The version that the mirror is upgraded from,
+    /// None if the mirror is built from the full archives
+    pub from_version: Option<String>,
+    /// The version that the mirror will have once every archive is downloaded
+    pub target_version: String,
+    /// The versions of the diffs in the plan, in the order they should be applied
+    pub diff_versions: Vec<String>,
+    /// Every archive in the plan
+    pub archives: Vec<AssetPathArchive>,
+    /// The total size of `archives` in bytes
+    pub total_bytes: u64,
+    /// How many fewer bytes are downloaded
+    /// compared to downloading the full archives and every diff
+    pub saved_bytes: u64,
+}
+
+impl DownloadPlan {
+    /// Plans the smallest download that upgrades a mirror at `from_version` to the target version
+    /// of `asset_paths`.
+    ///
+    /// The diffs are chained from `from_version` to the target version. If there is no chain,
+    /// or if `from_version` is None, the full archives are used with the chain of diffs from the
+    /// full archives' version instead. If that chain does not exist either,
+    /// the full archives and every diff are downloaded, with the diffs in version order.
+    pub fn new(asset_paths: &AssetPaths, from_version: Option<&str>) -> Self {
+        let target_version = &asset_paths.info.target_asset_version;
+        let all_archives_bytes = archives_size(
+            asset_paths
+                .full
+                .archive
+                .iter()
+                .chain(asset_paths.diff.iter().flat_map(|diff| &diff.archive)),
+        );
+
+        let diff_chain = from_version.and_then(|from_version| {
+            find_diff_chain(&asset_paths.diff, from_version, target_version)
+        });
+        let (from_version, diffs, full) = match diff_chain {
+            Some(diffs) => (from_version.map(String::from), diffs, false),
+            None => {
+                let diffs =
+                    find_diff_chain(&asset_paths.diff, &asset_paths.full.version, target_version)
+                        .unwrap_or_else(|| {
+                            // merged asset paths do not keep the diffs in any order
+                            let mut diffs: Vec<&AssetPathDiff> = asset_paths.diff.iter().collect();
+                            diffs.sort_by(|a, b| compare_versions(&a.version, &b.version));
+                            diffs
+                        });
+                (None, diffs, true)
+            }
+        };
+
+        let full_archives = asset_paths.full.archive.iter().filter(|_| full);
+        let archives: Vec<AssetPathArchive> = full_archives
+            .chain(diffs.iter().flat_map(|diff| &diff.archive))
+            .cloned()
+            .collect();
+        let total_bytes = archives_size(&archives);
+
+        Self {
+            from_version,
+            target_version: target_version.clone(),
+            diff_versions: diffs.iter().map(|diff| diff.version.clone()).collect(),
+            archives,
+            total_bytes,
+            saved_bytes: all_archives_bytes.saturating_sub(total_bytes),
+        }
+    }
+
+    /// Returns true if the plan uses the full archives instead of only diffs
+    pub fn is_full(&self) -> bool {
+        self.from_version.is_none()
+    }
+}
+
+/// Returns the combined size of `archives` in bytes
+fn archives_size<'a>(archives: impl IntoIterator<Item = &'a AssetPathArchive>) -> u64 {
+    archives.into_iter().map(|archive| archive.size).sum()
+}
+
+/// Compares asset versions such as "1.0.10" by each of their dot separated numbers,
+/// comparing parts that are not numbers as text
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_part), Some(b_part)) => match (a_part.parse::<u64>(), b_part.parse::<u64>()) {
+                (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number),
+                _ => a_part.cmp(b_part),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Finds the chain of diffs from `from_version` to `to_version` with the smallest download size.
+///
+/// Returns an empty chain if the versions are the same,
+/// and None if there is no chain between the versions.
+fn find_diff_chain<'a>(
+    diffs: &'a [AssetPathDiff],
+    from_version: &str,
+    to_version: &str,
+) -> Option<Vec<&'a AssetPathDiff>> {
+    let mut edges: HashMap<&str, Vec<&AssetPathDiff>> = HashMap::new();
+    for diff in diffs {
+        edges
+            .entry(diff.original_version.as_str())
+            .or_default()
+            .push(diff);
+    }
+
+    // dijkstra, weighted by the download size of each diff
+    let mut best_bytes: HashMap<&str, u64> = HashMap::from([(from_version, 0)]);
+    let mut previous: HashMap<&str, &AssetPathDiff> = HashMap::new();
+    let mut queue = BinaryHeap::from([Reverse((0, from_version))]);
+    while let Some(Reverse((bytes, version))) = queue.pop() {
+        if version == to_version {
+            break;
+        }
+        if best_bytes.get(version).is_some_and(|best| bytes > *best) {
+            continue;
+        }
+
+        for diff in edges.get(version).into_iter().flatten() {
+            let next_bytes = bytes + archives_size(&diff.archive);
+            let next_version = diff.version.as_str();
+            if best_bytes
+                .get(next_version)
+                .is_none_or(|best| next_bytes < *best)
+            {
+                best_bytes.insert(next_version, next_bytes);
+                previous.insert(next_version, diff);
+                queue.push(Reverse((next_bytes, next_version)));
+            }
+        }
+    }
+
+    if !best_bytes.contains_key(to_version) {
+        return None;
+    }
+
+    // walk the chain back to from_version
+    let mut chain = Vec::new();
+    let mut version = to_version;
+    while version != from_version {
+        let diff = previous[version];
+        chain.push(diff);
+        version = &diff.original_version;
+    }
+    chain.reverse();
+    Some(chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use starview_common::enums::DeviceType;
+    use starview_net::models::{AssetPathsFull, AssetPathsInfo};
+
+    use super::*;
+
+    fn archive(location: &str, size: u64) -> AssetPathArchive {
+        AssetPathArchive {
+            location: location.into(),
+            size,
+            sha256: location.into(),
+        }
+    }
+
+    fn diff(original_version: &str, version: &str, size: u64) -> AssetPathDiff {
+        AssetPathDiff {
+            version: version.into(),
+            original_version: original_version.into(),
+            archive: vec![archive(&format!("{original_version}-{version}"), size)],
+        }
+    }
+
+    fn asset_paths(diffs: Vec<AssetPathDiff>) -> AssetPaths {
+        AssetPaths {
+            info: AssetPathsInfo {
+                client_asset_version: "4".into(),
+                target_asset_version: "4".into(),
+                eventual_target_asset_version: "4".into(),
+                is_initial: false,
+                latest_maj_first_version: "1".into(),
+            },
+            full: AssetPathsFull {
+                version: "1".into(),
+                archive: vec![archive("full", 1000)],
+            },
+            diff: diffs,
+            asset_version_hash: String::new(),
+            device_type: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_diff_chain() {
+        let paths = asset_paths(vec![
+            diff("1", "2", 10),
+            diff("2", "3", 10),
+            diff("3", "4", 10),
+            diff("2", "4", 50),
+        ]);
+
+        let plan = DownloadPlan::new(&paths, Some("2"));
+        assert_eq!(plan.from_version.as_deref(), Some("2"));
+        assert_eq!(plan.diff_versions, vec!["3", "4"]);
+        assert_eq!(plan.total_bytes, 20);
+        assert_eq!(plan.saved_bytes, 1080 - 20);
+
+        let plan = DownloadPlan::new(&paths, Some("4"));
+        assert!(plan.archives.is_empty());
+        assert_eq!(plan.saved_bytes, 1080);
+    }
+
+    #[test]
+    fn test_plan_full_fallback() {
+        let paths = asset_paths(vec![diff("1", "2", 10), diff("2", "4", 10)]);
+
+        let plan = DownloadPlan::new(&paths, Some("0"));
+        assert!(plan.is_full());
+        assert_eq!(plan.diff_versions, vec!["2", "4"]);
+        assert_eq!(plan.total_bytes, 1020);
+
+        // no chain from the full archives, so every diff is downloaded
+        let paths = asset_paths(vec![diff("1", "2", 10), diff("3", "4", 10)]);
+        let plan = DownloadPlan::new(&paths, None);
+        assert!(plan.is_full());
+        assert_eq!(plan.total_bytes, 1020);
+        assert_eq!(plan.saved_bytes, 0);
+    }
+
+    #[test]
+    fn test_plan_fallback_version_order() {
+        // no chain from the full archives, with diffs listed in a different order for each device
+        let mut android = asset_paths(vec![diff("1", "1.0.2", 10), diff("1.0.9", "1.0.10", 10)]);
+        android.device_type = Some(DeviceType::Android);
+        let mut ios = asset_paths(vec![diff("1.0.9", "1.0.10", 10), diff("1", "1.0.2", 10)]);
+        ios.device_type = Some(DeviceType::Ios);
+
+        for paths in [android.clone().extend(ios.clone()), ios.extend(android)] {
+            let plan = DownloadPlan::new(&paths, None);
+            assert_eq!(plan.diff_versions, vec!["1.0.2", "1.0.10"]);
+        }
+        assert_eq!(compare_versions("1.0.9", "1.0.10"), Ordering::Less);
+        assert_eq!(compare_versions("1.1", "1.0.10"), Ordering::Greater);
+    }
+}
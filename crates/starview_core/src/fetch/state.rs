@@ -1,7 +1,9 @@
-use crate::download::state::DownloadState;
+use std::time::Duration;
+
+use crate::{download::state::DownloadState, fetch::DownloadPlan, mirror::state::ImportState};
 
 /// The state of a fetch asset info task
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum FetchAssetInfoState {
     /// The most recent asset version is being queried from the server
     GetAssetVersion,
@@ -12,10 +14,12 @@ pub enum FetchAssetInfoState {
 }
 
 /// The state of an asset download
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum DownloadAssetsState {
     /// Asset info is being retrieved
     FetchAssetInfo,
+    /// The archives that will be downloaded have been planned
+    Plan(DownloadPlan),
     /// The provided number of bytes will be downloaded
     DownloadStart(u64),
     /// A download state update
@@ -25,7 +29,7 @@ pub enum DownloadAssetsState {
 }
 
 /// The state of a files list download
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum DownloadFilesListState {
     /// Asset info is being retrieved
     FetchAssetInfo,
@@ -37,11 +41,38 @@ pub enum DownloadFilesListState {
     Finish,
 }
 
+/// The state of an asset import
+#[derive(Clone, Debug)]
+pub enum ImportAssetsState {
+    /// Asset info is being retrieved
+    FetchAssetInfo,
+    /// An import state update
+    Import(ImportState),
+    /// The assets import process has completed
+    Finish,
+}
+
+/// The state of a watch for new asset versions
+#[derive(Clone, Debug)]
+pub enum WatchState {
+    /// The server's asset version is being requested
+    Poll,
+    /// The server's asset version has not changed from the provided version
+    Unchanged(String),
+    /// The request failed, so the client is logging in again in case its session expired
+    Relogin,
+    /// The server could not be polled, it will be polled again after `retry_in`
+    PollFailed { error: String, retry_in: Duration },
+    /// The server has the provided new asset version
+    NewVersion(String),
+}
+
 /// The current state of a [`crate::fetch::Fetcher`]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum FetchState {
-    None,
     AssetInfo(FetchAssetInfoState),
     DownloadAssets(DownloadAssetsState),
     DownloadFilesList(DownloadFilesListState),
+    ImportAssets(ImportAssetsState),
+    Watch(WatchState),
 }
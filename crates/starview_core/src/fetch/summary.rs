@@ -0,0 +1,12 @@
+use crate::cache::models::FailedDownload;
+
+/// The result of downloading assets with a [`crate::fetch::Fetcher`]
+#[derive(Clone, Debug, Default)]
+pub struct DownloadSummary {
+    /// The number of assets that were downloaded
+    pub downloaded: usize,
+    /// Assets that could not be downloaded
+    pub failed: Vec<FailedDownload>,
+    /// Whether the download was cancelled before every asset was downloaded
+    pub cancelled: bool,
+}
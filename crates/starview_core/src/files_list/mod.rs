@@ -0,0 +1,7 @@
+mod models;
+mod query;
+mod resolve;
+
+pub use models::{FilesList, FilesListEntry};
+pub use query::{FilesListQuery, PathPattern};
+pub use resolve::{LinkMode, ResolveReport, resolve_logical_paths};
@@ -0,0 +1,165 @@
+use std::{io::Read, path::Path};
+
+use csv::{ReaderBuilder, Trim};
+use serde::{Deserialize, Serialize};
+use starview_common::enums::DeviceType;
+
+use crate::{Error, cache::models::FetchCache, files_list::FilesListQuery, mirror};
+
+/// A single file listed in a files list
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilesListEntry {
+    /// The logical path of the file in the game
+    pub path: String,
+    pub hash: String,
+    /// The size of the file in bytes
+    pub size: u64,
+    /// Every column after the size
+    pub flags: Vec<String>,
+    /// The device type of the files list that this entry is from
+    pub device_type: Option<DeviceType>,
+}
+
+/// The rows of one or more files list CSVs
+#[derive(Clone, Debug, Default)]
+pub struct FilesList {
+    pub entries: Vec<FilesListEntry>,
+}
+
+impl FilesList {
+    /// Parses a files list CSV, tagging every entry with `device_type`.
+    ///
+    /// Each row contains a path, hash, and size followed by any number of flags.
+    /// A header row is skipped if one is present.
+    pub fn parse(reader: impl Read, device_type: Option<DeviceType>) -> Result<Self, Error> {
+        let mut csv_reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .trim(Trim::All)
+            .from_reader(reader);
+
+        let mut entries: Vec<FilesListEntry> = Vec::new();
+        for (index, record) in csv_reader.records().enumerate() {
+            let record = record?;
+            let line = record.position().map(|pos| pos.line()).unwrap_or(0);
+            if record.len() < 3 {
+                return Err(Error::InvalidFilesListRow {
+                    line,
+                    reason: format!("expected at least 3 columns, got {}", record.len()),
+                });
+            }
+
+            let size = match record[2].parse::<u64>() {
+                Ok(size) => size,
+                // the first row is a header if its size is not a number
+                Err(_) if index == 0 => continue,
+                Err(err) => {
+                    return Err(Error::InvalidFilesListRow {
+                        line,
+                        reason: format!("invalid size '{}': {}", &record[2], err),
+                    });
+                }
+            };
+
+            entries.push(FilesListEntry {
+                path: record[0].into(),
+                hash: record[1].into(),
+                size,
+                flags: record.iter().skip(3).map(String::from).collect(),
+                device_type,
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Loads the files list CSV at `path`, tagging every entry with `device_type`
+    pub async fn from_path(
+        path: impl AsRef<Path>,
+        device_type: Option<DeviceType>,
+    ) -> Result<Self, Error> {
+        let file_bytes = tokio::fs::read(path).await?;
+        Self::parse(file_bytes.as_slice(), device_type)
+    }
+
+    /// Loads every files list of `asset_version` that was downloaded to `lists_path`,
+    /// combining the lists of every device type into one FilesList.
+    pub async fn for_version(
+        cache: &FetchCache,
+        asset_version: &str,
+        lists_path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        let cached_version = cache
+            .versions
+            .get(asset_version)
+            .ok_or_else(|| Error::UnknownAssetVersion(asset_version.into()))?;
+
+        let mut files_list = Self::default();
+        for version_info in &cached_version.version_info {
+            let list_path = mirror::files_list_path(
+                lists_path.as_ref(),
+                &cached_version.asset_paths.info.client_asset_version,
+                &version_info.files_list,
+            )?;
+            if !list_path.is_file() {
+                return Err(Error::MissingFilesList(
+                    list_path.to_string_lossy().to_string(),
+                ));
+            }
+
+            let device_type = version_info
+                .device_type
+                .or(Some(cached_version.device_type));
+            let device_list = Self::from_path(&list_path, device_type).await?;
+            files_list.entries.extend(device_list.entries);
+        }
+
+        Ok(files_list)
+    }
+
+    /// Returns every entry that matches `query`
+    pub fn query<'a>(
+        &'a self,
+        query: &'a FilesListQuery,
+    ) -> impl Iterator<Item = &'a FilesListEntry> {
+        self.entries.iter().filter(|entry| query.matches(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let csv = "path,hash,size,flags\n\
+            character/a.png, 0a1b, 120, 1\n\
+            sound/b.ogg,2c3d,4500\n";
+        let files_list = FilesList::parse(csv.as_bytes(), Some(DeviceType::Ios)).unwrap();
+
+        assert_eq!(
+            files_list.entries,
+            vec![
+                FilesListEntry {
+                    path: "character/a.png".into(),
+                    hash: "0a1b".into(),
+                    size: 120,
+                    flags: vec!["1".into()],
+                    device_type: Some(DeviceType::Ios),
+                },
+                FilesListEntry {
+                    path: "sound/b.ogg".into(),
+                    hash: "2c3d".into(),
+                    size: 4500,
+                    flags: Vec::new(),
+                    device_type: Some(DeviceType::Ios),
+                },
+            ]
+        );
+
+        assert!(matches!(
+            FilesList::parse("a,b,c\nd,e,f\n".as_bytes(), None),
+            Err(Error::InvalidFilesListRow { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,94 @@
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use starview_common::enums::DeviceType;
+
+use crate::{Error, files_list::FilesListEntry};
+
+/// A pattern that is matched against the path of a files list entry
+#[derive(Clone, Debug)]
+pub enum PathPattern {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl PathPattern {
+    /// Creates a pattern that matches paths with a glob, such as `character/**/*.png`
+    pub fn glob(pattern: &str) -> Result<Self, Error> {
+        Ok(Self::Glob(Glob::new(pattern)?.compile_matcher()))
+    }
+
+    /// Creates a pattern that matches paths containing a match for a regular expression
+    pub fn regex(pattern: &str) -> Result<Self, Error> {
+        Ok(Self::Regex(Regex::new(pattern)?))
+    }
+
+    /// Returns true if `path` matches this pattern
+    pub fn is_match(&self, path: &str) -> bool {
+        match self {
+            Self::Glob(matcher) => matcher.is_match(path),
+            Self::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+/// Filters the entries of a [`crate::files_list::FilesList`]
+///
+/// The default query matches every entry.
+#[derive(Clone, Debug, Default)]
+pub struct FilesListQuery {
+    /// Only match entries with a path that matches this pattern
+    pub pattern: Option<PathPattern>,
+    /// Only match entries from the files list of this device type
+    pub device_type: Option<DeviceType>,
+}
+
+impl FilesListQuery {
+    /// Returns true if `entry` matches this query
+    pub fn matches(&self, entry: &FilesListEntry) -> bool {
+        let device_matches = match self.device_type {
+            None | Some(DeviceType::All) => true,
+            Some(device_type) => entry.device_type == Some(device_type),
+        };
+
+        device_matches
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(&entry.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, device_type: DeviceType) -> FilesListEntry {
+        FilesListEntry {
+            path: path.into(),
+            hash: String::new(),
+            size: 0,
+            flags: Vec::new(),
+            device_type: Some(device_type),
+        }
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let png = entry("character/a/face.png", DeviceType::Android);
+        let ogg = entry("sound/bgm.ogg", DeviceType::Ios);
+
+        let query = FilesListQuery {
+            pattern: Some(PathPattern::glob("character/**/*.png").unwrap()),
+            device_type: None,
+        };
+        assert!(query.matches(&png));
+        assert!(!query.matches(&ogg));
+
+        let query = FilesListQuery {
+            pattern: Some(PathPattern::regex(r"\.(png|ogg)$").unwrap()),
+            device_type: Some(DeviceType::Ios),
+        };
+        assert!(!query.matches(&png));
+        assert!(query.matches(&ogg));
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{copy, create_dir_all, hard_link, remove_file},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Serialize;
+use tokio::task::spawn_blocking;
+use walkdir::WalkDir;
+
+use crate::{Error, files_list::FilesListEntry};
+
+/// How files are created in a logical path tree
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkMode {
+    /// Hardlink files, copying them if they can not be hardlinked
+    #[default]
+    Hardlink,
+    /// Copy files
+    Copy,
+}
+
+/// The result of resolving files list entries to their logical paths
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ResolveReport {
+    /// The number of files that were created in the logical path tree
+    pub resolved: usize,
+    /// The number of files that were copied because they could not be hardlinked
+    pub copied: usize,
+    /// Entries with a hash that was not found in the extracted asset tree
+    pub missing: Vec<FilesListEntry>,
+    /// Entries with a path that would be written outside of the logical path tree
+    pub invalid: Vec<FilesListEntry>,
+}
+
+/// Creates a tree of files organized by their logical path in `out_path`
+/// from an extracted asset tree that stores files under their hashed names.
+///
+/// Files in `extracted_path` are matched to `entries` by file name, with or without an extension.
+///
+/// If `entries` contains entries of multiple device types,
+/// every device's files are written to a directory named after the device.
+pub async fn resolve_logical_paths(
+    entries: Vec<FilesListEntry>,
+    extracted_path: impl AsRef<Path>,
+    out_path: impl AsRef<Path>,
+    link_mode: LinkMode,
+) -> Result<ResolveReport, Error> {
+    let extracted_path = extracted_path.as_ref().to_path_buf();
+    let out_path = out_path.as_ref().to_path_buf();
+    spawn_blocking(move || resolve_blocking(entries, &extracted_path, &out_path, link_mode)).await?
+}
+
+fn resolve_blocking(
+    entries: Vec<FilesListEntry>,
+    extracted_path: &Path,
+    out_path: &Path,
+    link_mode: LinkMode,
+) -> Result<ResolveReport, Error> {
+    let hashed_files = index_hashed_files(extracted_path)?;
+    let device_types: HashSet<_> = entries.iter().map(|entry| entry.device_type).collect();
+    let per_device = device_types.len() > 1;
+
+    let mut report = ResolveReport::default();
+    for entry in entries {
+        let Some(logical_path) = logical_path(&entry, per_device) else {
+            report.invalid.push(entry);
+            continue;
+        };
+        let Some(hashed_path) = hashed_files.get(&entry.hash.to_lowercase()) else {
+            report.missing.push(entry);
+            continue;
+        };
+
+        let file_path = out_path.join(logical_path);
+        if let Some(parent) = file_path.parent() {
+            create_dir_all(parent)?;
+        }
+        if file_path.exists() {
+            remove_file(&file_path)?;
+        }
+
+        let linked = link_mode == LinkMode::Hardlink && hard_link(hashed_path, &file_path).is_ok();
+        if !linked {
+            copy(hashed_path, &file_path)?;
+            report.copied += 1;
+        }
+        report.resolved += 1;
+    }
+
+    Ok(report)
+}
+
+/// Maps the lowercase name of every file in `extracted_path` to its path.
+///
+/// Files are indexed both with and without their extension.
+fn index_hashed_files(extracted_path: &Path) -> Result<HashMap<String, PathBuf>, Error> {
+    let mut hashed_files: HashMap<String, PathBuf> = HashMap::new();
+    for dir_entry in WalkDir::new(extracted_path) {
+        let dir_entry = dir_entry.map_err(std::io::Error::from)?;
+        if !dir_entry.file_type().is_file() {
+            continue;
+        }
+
+        let path = dir_entry.path();
+        if let Some(file_stem) = path.file_stem() {
+            hashed_files
+                .entry(file_stem.to_string_lossy().to_lowercase())
+                .or_insert_with(|| path.to_path_buf());
+        }
+        hashed_files.insert(
+            dir_entry.file_name().to_string_lossy().to_lowercase(),
+            path.to_path_buf(),
+        );
+    }
+    Ok(hashed_files)
+}
+
+/// Returns the relative path that `entry` is written to,
+/// or None if its path would be written outside of the logical path tree.
+fn logical_path(entry: &FilesListEntry, per_device: bool) -> Option<PathBuf> {
+    let path = Path::new(&entry.path);
+    let is_relative = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !is_relative || entry.path.is_empty() {
+        return None;
+    }
+
+    let device_dir = entry
+        .device_type
+        .filter(|_| per_device)
+        .map(|device_type| PathBuf::from(device_type.name()))
+        .unwrap_or_default();
+    Some(device_dir.join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use starview_common::enums::DeviceType;
+
+    use super::*;
+
+    fn entry(path: &str, hash: &str, device_type: DeviceType) -> FilesListEntry {
+        FilesListEntry {
+            path: path.into(),
+            hash: hash.into(),
+            size: 0,
+            flags: Vec::new(),
+            device_type: Some(device_type),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_logical_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let extracted_path = dir.path().join("extracted");
+        create_dir_all(extracted_path.join("ab")).unwrap();
+        std::fs::write(extracted_path.join("ab/abcd.bin"), "png").unwrap();
+
+        let out_path = dir.path().join("out");
+        let entries = vec![
+            entry("character/a.png", "ABCD", DeviceType::Android),
+            entry("character/b.png", "ef01", DeviceType::Android),
+            entry("../escape.png", "abcd", DeviceType::Android),
+        ];
+        let report = resolve_logical_paths(entries, &extracted_path, &out_path, LinkMode::Copy)
+            .await
+            .unwrap();
+
+        assert_eq!(report.resolved, 1);
+        assert_eq!(report.missing[0].path, "character/b.png");
+        assert_eq!(report.invalid[0].path, "../escape.png");
+        assert_eq!(
+            std::fs::read_to_string(out_path.join("character/a.png")).unwrap(),
+            "png"
+        );
+    }
+}
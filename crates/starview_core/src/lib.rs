@@ -1,7 +1,10 @@
-mod cache;
-
+pub mod cache;
+pub mod changelog;
 pub mod download;
 pub mod error;
+pub mod extract;
 pub mod fetch;
+pub mod files_list;
+pub mod mirror;
 
 pub use error::Error;
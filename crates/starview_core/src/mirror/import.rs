@@ -0,0 +1,308 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use futures_util::{StreamExt, stream};
+use serde::Serialize;
+use sha2::Digest;
+use starview_net::models::AssetPathArchive;
+use tokio::{fs::rename, sync::mpsc, task::spawn_blocking};
+use walkdir::WalkDir;
+
+use crate::{
+    Error,
+    download::Downloader,
+    mirror::{self, MirrorPaths, state::ImportState},
+};
+
+/// An archive that was found in the asset directory but not imported,
+/// because a different file is where it would be stored
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportConflict {
+    pub archive: AssetPathArchive,
+    /// The file that is in the way of the archive
+    pub path: PathBuf,
+}
+
+/// A file that was in the way of an imported archive and was moved aside
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportBackup {
+    /// Where the file was
+    pub path: PathBuf,
+    /// Where the file was moved to
+    pub backup_path: PathBuf,
+}
+
+/// The result of importing an asset directory with an [`Importer`]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportReport {
+    /// Archives that were found in the asset directory
+    pub imported: Vec<AssetPathArchive>,
+    /// The number of archives that were found at a different path
+    /// and linked to where they would have been downloaded, leaving the found file in place
+    pub relocated: usize,
+    /// The number of files that were hashed
+    pub hashed_files: usize,
+    /// Files that are in the way of an archive, so the archive was not imported.
+    /// Empty if the importer is forced
+    pub conflicts: Vec<ImportConflict>,
+    /// Files that were in the way of an archive and moved aside because the importer is forced
+    pub backups: Vec<ImportBackup>,
+}
+
+/// Interface for finding archives that already exist in an asset directory
+pub struct Importer {
+    state_sender: mpsc::UnboundedSender<ImportState>,
+    archives: Vec<AssetPathArchive>,
+    mirror: MirrorPaths,
+    concurrency: usize,
+    /// If files in the way of an archive are moved aside instead of reported as conflicts
+    force: bool,
+}
+
+impl Importer {
+    /// Creates a new Importer that searches `mirror` for `archives`,
+    /// hashing up to `concurrency` files at a time.
+    ///
+    /// Every state update is sent to the returned receiver.
+    pub fn new(
+        archives: Vec<AssetPathArchive>,
+        mirror: MirrorPaths,
+        concurrency: usize,
+    ) -> (Self, mpsc::UnboundedReceiver<ImportState>) {
+        let (state_sender, recv) = mpsc::unbounded_channel();
+
+        (
+            Self {
+                state_sender,
+                archives,
+                mirror,
+                concurrency,
+                force: false,
+            },
+            recv,
+        )
+    }
+
+    /// Sets if files in the way of an archive are moved aside
+    /// instead of being reported as conflicts
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Hashes every file in the mirror with the size of an archive,
+    /// matching files to archives by their size and sha256.
+    ///
+    /// Matching files are linked, or copied if they can not be linked, to every path
+    /// the archive is stored at, so that the mirror has the same layout as a downloaded one.
+    /// Files are never moved, replaced, or deleted.
+    ///
+    /// An archive is not imported if a different file is at one of its paths,
+    /// unless the importer is forced, which moves that file aside with a `.bak` extension.
+    pub async fn import(self) -> Result<ImportReport, Error> {
+        let mut archives: HashMap<(u64, String), AssetPathArchive> = HashMap::new();
+        for archive in self.archives {
+            archives.insert((archive.size, archive.sha256.to_lowercase()), archive);
+        }
+
+        // only files with the size of an archive can be an archive
+        let archive_sizes: HashSet<u64> = archives.keys().map(|(size, _)| *size).collect();
+        let mirror_path = self.mirror.mirror_path().to_path_buf();
+        let candidates =
+            spawn_blocking(move || find_candidate_files(&mirror_path, &archive_sizes)).await??;
+
+        let total_bytes = candidates.iter().map(|(_, size)| size).sum();
+        let _ = self
+            .state_sender
+            .send(ImportState::ImportStart(total_bytes));
+
+        let hashed_files: Vec<Result<(PathBuf, u64, String), Error>> = stream::iter(candidates)
+            .map(|(path, size)| {
+                let state_sender = self.state_sender.clone();
+                async move {
+                    let sha256 = hex::encode(Downloader::hash_file(&path).await?.finalize());
+                    let _ = state_sender.send(ImportState::FileHash {
+                        path: path.clone(),
+                        size,
+                    });
+                    Ok((path, size, sha256))
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut report = ImportReport {
+            hashed_files: hashed_files.len(),
+            ..Default::default()
+        };
+
+        // every copy of each archive that was found, so copies that are already in place are kept
+        let mut found_paths: HashMap<(u64, String), Vec<PathBuf>> = HashMap::new();
+        for hashed_file in hashed_files {
+            let (path, size, sha256) = hashed_file?;
+            let key = (size, sha256);
+            if archives.contains_key(&key) {
+                found_paths.entry(key).or_default().push(path);
+            }
+        }
+
+        for (key, paths) in found_paths {
+            let Some(archive) = archives.remove(&key) else {
+                continue;
+            };
+
+            let archive_paths = self.mirror.archive_paths(&archive)?;
+            let conflicts: Vec<PathBuf> = archive_paths
+                .iter()
+                .filter(|archive_path| archive_path.exists() && !paths.contains(archive_path))
+                .cloned()
+                .collect();
+            if !conflicts.is_empty() && !self.force {
+                for path in conflicts {
+                    let _ = self.state_sender.send(ImportState::FileConflict {
+                        archive: archive.clone(),
+                        path: path.clone(),
+                    });
+                    report.conflicts.push(ImportConflict {
+                        archive: archive.clone(),
+                        path,
+                    });
+                }
+                continue;
+            }
+            for path in conflicts {
+                let backup_path = backup_path(&path);
+                rename(&path, &backup_path).await?;
+                report.backups.push(ImportBackup { path, backup_path });
+            }
+
+            let archive_path = &archive_paths[0];
+            if !archive_path.exists() {
+                mirror::link_file(&paths[0], archive_path).await?;
+                report.relocated += 1;
+            }
+            self.mirror.link_missing(&archive).await?;
+
+            let _ = self.state_sender.send(ImportState::FileImport {
+                archive: archive.clone(),
+                path: archive_path.clone(),
+            });
+            report.imported.push(archive);
+        }
+
+        let _ = self.state_sender.send(ImportState::Finish);
+        Ok(report)
+    }
+}
+
+/// Returns a path next to `path` with a `.bak` extension that no file is at
+fn backup_path(path: &Path) -> PathBuf {
+    let mut suffix: usize = 0;
+    loop {
+        let mut backup_path = path.as_os_str().to_owned();
+        match suffix {
+            0 => backup_path.push(".bak"),
+            _ => backup_path.push(format!(".bak.{suffix}")),
+        }
+        let backup_path = PathBuf::from(backup_path);
+        if !backup_path.exists() {
+            return backup_path;
+        }
+        suffix += 1;
+    }
+}
+
+/// Returns the path and size of every file in `mirror_path` with a size in `archive_sizes`
+fn find_candidate_files(
+    mirror_path: &Path,
+    archive_sizes: &HashSet<u64>,
+) -> Result<Vec<(PathBuf, u64)>, Error> {
+    let mut candidates = Vec::new();
+    for dir_entry in WalkDir::new(mirror_path) {
+        let dir_entry = dir_entry.map_err(std::io::Error::from)?;
+        if !dir_entry.file_type().is_file() {
+            continue;
+        }
+
+        let size = dir_entry.metadata().map_err(std::io::Error::from)?.len();
+        if archive_sizes.contains(&size) {
+            candidates.push((dir_entry.into_path(), size));
+        }
+    }
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use starview_common::enums::{DeviceType, MirrorLayout};
+
+    use super::*;
+    use crate::mirror::test_utils::archive;
+
+    #[tokio::test]
+    async fn test_import() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("full")).unwrap();
+        std::fs::write(dir.path().join("full/a.zip"), "aaaa").unwrap();
+        std::fs::write(dir.path().join("copied-b.zip"), "bbbb").unwrap();
+        std::fs::write(dir.path().join("other.zip"), "cccc").unwrap();
+
+        let archives = vec![
+            archive("full/a.zip", "aaaa"),
+            archive("diff/b.zip", "bbbb"),
+            archive("diff/d.zip", "dddddd"),
+        ];
+        let mirror = MirrorPaths::new(dir.path(), MirrorLayout::Merged, DeviceType::All);
+        let (importer, _) = Importer::new(archives, mirror, 2);
+        let report = importer.import().await.unwrap();
+
+        assert_eq!(report.imported.len(), 2);
+        assert_eq!(report.relocated, 1);
+        assert_eq!(report.hashed_files, 3);
+        assert!(dir.path().join("diff/b.zip").is_file());
+        // the found copy is left where it was
+        assert!(dir.path().join("copied-b.zip").is_file());
+    }
+
+    #[tokio::test]
+    async fn test_import_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("full")).unwrap();
+        std::fs::write(dir.path().join("full/a.zip"), "old!").unwrap();
+        std::fs::write(dir.path().join("copied-a.zip"), "aaaa").unwrap();
+
+        let archives = vec![archive("full/a.zip", "aaaa")];
+        let mirror = MirrorPaths::new(dir.path(), MirrorLayout::Merged, DeviceType::All);
+        let (importer, _) = Importer::new(archives.clone(), mirror.clone(), 2);
+        let report = importer.import().await.unwrap();
+
+        assert!(report.imported.is_empty());
+        assert_eq!(report.conflicts[0].path, dir.path().join("full/a.zip"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("full/a.zip")).unwrap(),
+            "old!"
+        );
+
+        let (importer, _) = Importer::new(archives, mirror, 2);
+        let report = importer.force(true).import().await.unwrap();
+
+        assert_eq!(report.imported.len(), 1);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("full/a.zip")).unwrap(),
+            "aaaa"
+        );
+        // the file that was in the way is moved aside, not deleted
+        assert_eq!(
+            report.backups[0].backup_path,
+            dir.path().join("full/a.zip.bak")
+        );
+        assert_eq!(
+            std::fs::read_to_string(&report.backups[0].backup_path).unwrap(),
+            "old!"
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use starview_common::{
+    enums::{DeviceType, MirrorLayout},
+    fs::write_file,
+};
+use starview_net::models::{AssetPathArchive, AssetPathsInfo};
+use tokio::fs::remove_file;
+
+use crate::{
+    Error,
+    cache::models::{CachedVersion, FetchCache},
+    mirror,
+};
+
+/// The directory of a content-addressed mirror that archives are stored in
+const OBJECTS_DIR: &str = "objects";
+
+/// The directory of a content-addressed mirror that device indexes are stored in
+const INDEXES_DIR: &str = "indexes";
+
+/// An archive in a [`DeviceIndex`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedArchive {
+    #[serde(flatten)]
+    pub archive: AssetPathArchive,
+    /// Where the archive is stored, relative to the mirror
+    pub path: PathBuf,
+}
+
+/// The archives that build an asset version for a single device in a content-addressed mirror
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceIndex {
+    pub device_type: DeviceType,
+    pub info: AssetPathsInfo,
+    /// The full archives followed by the diffs leading to the asset version, in the order they are applied
+    pub archives: Vec<IndexedArchive>,
+}
+
+/// Resolves where archives are stored in a mirror with a [`MirrorLayout`]
+#[derive(Clone, Debug)]
+pub struct MirrorPaths {
+    mirror_path: PathBuf,
+    layout: MirrorLayout,
+    /// The devices that use each archive, keyed by sha256
+    archive_devices: HashMap<String, Vec<DeviceType>>,
+    /// The devices used for archives that are not in `archive_devices`
+    default_devices: Vec<DeviceType>,
+}
+
+impl MirrorPaths {
+    /// Creates a new MirrorPaths for the mirror at `mirror_path`,
+    /// using the layout and archive devices recorded in `cache`
+    pub fn for_cache(cache: &FetchCache, mirror_path: impl AsRef<Path>) -> Self {
+        Self::new(mirror_path, cache.mirror_layout, cache.device_type)
+            .archive_devices(cache.archive_devices())
+    }
+
+    /// Creates a new MirrorPaths for the mirror at `mirror_path` with `layout`,
+    /// where every archive is used by `device_type`
+    pub fn new(
+        mirror_path: impl AsRef<Path>,
+        layout: MirrorLayout,
+        device_type: DeviceType,
+    ) -> Self {
+        Self {
+            mirror_path: mirror_path.as_ref().to_path_buf(),
+            layout,
+            archive_devices: HashMap::new(),
+            default_devices: device_type.devices().to_vec(),
+        }
+    }
+
+    /// Sets the devices that use each archive, keyed by sha256
+    pub fn archive_devices(mut self, archive_devices: HashMap<String, Vec<DeviceType>>) -> Self {
+        self.archive_devices = archive_devices;
+        self
+    }
+
+    /// Returns the path of the mirror
+    pub fn mirror_path(&self) -> &Path {
+        &self.mirror_path
+    }
+
+    /// Returns the layout of the mirror
+    pub fn layout(&self) -> MirrorLayout {
+        self.layout
+    }
+
+    /// Returns every path that `archive` is stored at.
+    ///
+    /// The first path is where the archive is downloaded to,
+    /// the rest are hardlinks to it created by [`MirrorPaths::link_archive`].
+    pub fn archive_paths(&self, archive: &AssetPathArchive) -> Result<Vec<PathBuf>, Error> {
+        match self.layout {
+            MirrorLayout::Merged => Ok(vec![mirror::archive_path(&self.mirror_path, archive)?]),
+            MirrorLayout::PerDevice => self
+                .devices(archive)
+                .iter()
+                .map(|device_type| {
+                    mirror::archive_path(&self.mirror_path.join(device_type.name()), archive)
+                })
+                .collect(),
+            MirrorLayout::ContentAddressed => {
+                let sha256 = archive.sha256.to_lowercase();
+                let prefix: String = sha256.chars().take(2).collect();
+                Ok(vec![
+                    self.mirror_path.join(OBJECTS_DIR).join(prefix).join(sha256),
+                ])
+            }
+        }
+    }
+
+    /// Returns the path that `archive` is downloaded to
+    pub fn archive_path(&self, archive: &AssetPathArchive) -> Result<PathBuf, Error> {
+        let mut archive_paths = self.archive_paths(archive)?;
+        Ok(archive_paths.swap_remove(0))
+    }
+
+    /// Hardlinks the downloaded copy of `archive` to every other path that it is stored at,
+    /// copying it if it can not be hardlinked
+    pub async fn link_archive(&self, archive: &AssetPathArchive) -> Result<(), Error> {
+        let archive_paths = self.archive_paths(archive)?;
+        let Some((source_path, link_paths)) = archive_paths.split_first() else {
+            return Ok(());
+        };
+
+        for link_path in link_paths {
+            if link_path.exists() {
+                remove_file(link_path).await?;
+            }
+            mirror::link_file(source_path, link_path).await?;
+        }
+        Ok(())
+    }
+
+    /// Hardlinks an existing copy of `archive` to the paths that it is stored at that do not exist,
+    /// such as the tree of a device that was added after the archive was downloaded
+    pub async fn link_missing(&self, archive: &AssetPathArchive) -> Result<(), Error> {
+        let archive_paths = self.archive_paths(archive)?;
+        let Some(source_path) = archive_paths.iter().find(|path| path.is_file()) else {
+            return Ok(());
+        };
+
+        for link_path in archive_paths.iter().filter(|path| !path.exists()) {
+            mirror::link_file(source_path, link_path).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns where the index of `asset_version` for `device_type` is written
+    /// in a content-addressed mirror
+    pub fn index_path(&self, device_type: DeviceType, asset_version: &str) -> PathBuf {
+        self.mirror_path
+            .join(INDEXES_DIR)
+            .join(device_type.name())
+            .join(format!("{asset_version}.json"))
+    }
+
+    /// Returns the path of every index that may be written for `cached_version`,
+    /// or nothing if the mirror is not content-addressed
+    pub fn index_paths(&self, asset_version: &str, cached_version: &CachedVersion) -> Vec<PathBuf> {
+        if self.layout != MirrorLayout::ContentAddressed {
+            return Vec::new();
+        }
+        cached_version
+            .device_type
+            .devices()
+            .iter()
+            .map(|device_type| self.index_path(*device_type, asset_version))
+            .collect()
+    }
+
+    /// Writes an index of the archives that build `asset_version` for every device it was fetched for,
+    /// if the mirror is content-addressed, see [`FetchCache::mirror_archives`].
+    ///
+    /// Returns the paths of the written indexes.
+    pub async fn write_indexes(
+        &self,
+        cache: &FetchCache,
+        asset_version: &str,
+    ) -> Result<Vec<PathBuf>, Error> {
+        if self.layout != MirrorLayout::ContentAddressed {
+            return Ok(Vec::new());
+        }
+        let cached_version = cache
+            .versions
+            .get(asset_version)
+            .ok_or_else(|| Error::UnknownAssetVersion(asset_version.into()))?;
+
+        let device_asset_paths = if cached_version.device_asset_paths.is_empty() {
+            std::slice::from_ref(&cached_version.asset_paths)
+        } else {
+            cached_version.device_asset_paths.as_slice()
+        };
+
+        let mut index_paths = Vec::with_capacity(device_asset_paths.len());
+        for asset_paths in device_asset_paths {
+            let device_type = asset_paths
+                .device_type
+                .unwrap_or(cached_version.device_type);
+            let mirror_archives = cache
+                .mirror_archives(asset_version, Some(device_type))
+                .unwrap_or_default();
+
+            let mut archives = Vec::with_capacity(mirror_archives.len());
+            for archive in mirror_archives {
+                let path = self.archive_path(&archive)?;
+                archives.push(IndexedArchive {
+                    path: path
+                        .strip_prefix(&self.mirror_path)
+                        .unwrap_or(&path)
+                        .to_path_buf(),
+                    archive,
+                });
+            }
+            let index = DeviceIndex {
+                device_type,
+                info: asset_paths.info.clone(),
+                archives,
+            };
+
+            let index_path = self.index_path(device_type, asset_version);
+            write_file(&serde_json::to_vec_pretty(&index)?, &index_path).await?;
+            index_paths.push(index_path);
+        }
+        Ok(index_paths)
+    }
+
+    /// Returns the devices that use `archive`
+    fn devices(&self, archive: &AssetPathArchive) -> &[DeviceType] {
+        self.archive_devices
+            .get(&archive.sha256)
+            .filter(|device_types| !device_types.is_empty())
+            .unwrap_or(&self.default_devices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_paths() {
+        let archive = AssetPathArchive {
+            location: "https://cdn.example.com/patch/gf/upload_assets/full/a.zip".into(),
+            size: 1,
+            sha256: "ABCD".into(),
+        };
+        let ios_archive = AssetPathArchive {
+            sha256: "ef".into(),
+            ..archive.clone()
+        };
+        let archive_devices = HashMap::from([("ef".to_string(), vec![DeviceType::Ios])]);
+
+        let merged = MirrorPaths::new("m", MirrorLayout::Merged, DeviceType::All);
+        assert_eq!(
+            merged.archive_paths(&archive).unwrap(),
+            vec![PathBuf::from("m/full/a.zip")]
+        );
+
+        let per_device = MirrorPaths::new("m", MirrorLayout::PerDevice, DeviceType::All)
+            .archive_devices(archive_devices);
+        assert_eq!(
+            per_device.archive_paths(&archive).unwrap(),
+            vec![
+                PathBuf::from("m/android/full/a.zip"),
+                PathBuf::from("m/ios/full/a.zip")
+            ]
+        );
+        assert_eq!(
+            per_device.archive_paths(&ios_archive).unwrap(),
+            vec![PathBuf::from("m/ios/full/a.zip")]
+        );
+
+        let content_addressed =
+            MirrorPaths::new("m", MirrorLayout::ContentAddressed, DeviceType::All);
+        assert_eq!(
+            content_addressed.archive_path(&archive).unwrap(),
+            PathBuf::from("m/objects/ab/abcd")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_link_missing_into_added_device() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = mirror::test_utils::archive("full/a.zip", "a");
+
+        // the archive was downloaded when the mirror only had ios
+        let ios_only = MirrorPaths::new(dir.path(), MirrorLayout::PerDevice, DeviceType::Ios);
+        let ios_path = ios_only.archive_path(&archive).unwrap();
+        write_file(b"a", &ios_path).await.unwrap();
+
+        let per_device = MirrorPaths::new(dir.path(), MirrorLayout::PerDevice, DeviceType::All);
+        per_device.link_missing(&archive).await.unwrap();
+        let android_path = dir
+            .path()
+            .join("android")
+            .join(ios_path.strip_prefix(dir.path().join("ios")).unwrap());
+        assert_eq!(std::fs::read(android_path).unwrap(), b"a");
+        assert_eq!(std::fs::read(ios_path).unwrap(), b"a");
+    }
+}
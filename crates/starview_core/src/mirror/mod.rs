@@ -0,0 +1,111 @@
+mod import;
+mod layout;
+mod prune;
+mod verify;
+
+pub mod state;
+
+pub use import::{ImportBackup, ImportConflict, ImportReport, Importer};
+pub use layout::{DeviceIndex, IndexedArchive, MirrorPaths};
+pub use prune::{PruneReport, PrunedFile, prune};
+pub use verify::{ArchiveStatus, CorruptArchive, Verifier, VerifyReport};
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use starview_net::models::AssetPathArchive;
+use tokio::fs::{copy, create_dir_all, hard_link};
+use url::Url;
+use walkdir::WalkDir;
+
+use crate::{Error, download::Downloader};
+
+/// Stripped from the path of an asset's URL to get its path in a mirror
+pub const ASSET_URL_STRIP_PREFIX: &str = "/patch/gf/upload_assets";
+
+/// Stripped from the path of a files list's URL to get its path in an asset version's directory
+pub const FILES_LIST_URL_STRIP_PREFIX: &str = "/patch/gf/upload_assets/entities";
+
+/// Returns where `archive` is saved in the mirror at `mirror_path` with the merged layout,
+/// see [`MirrorPaths`] for other layouts
+pub fn archive_path(mirror_path: &Path, archive: &AssetPathArchive) -> Result<PathBuf, Error> {
+    let url = Url::from_str(&archive.location)?;
+    Ok(Downloader::get_url_out_path(
+        &url,
+        mirror_path,
+        &Some(ASSET_URL_STRIP_PREFIX.into()),
+    ))
+}
+
+/// Returns where the files list at `files_list_url` of `asset_version` is saved in `lists_path`
+pub fn files_list_path(
+    lists_path: &Path,
+    asset_version: &str,
+    files_list_url: &str,
+) -> Result<PathBuf, Error> {
+    let url = Url::from_str(files_list_url)?;
+    Ok(Downloader::get_url_out_path(
+        &url,
+        &lists_path.join(asset_version),
+        &Some(FILES_LIST_URL_STRIP_PREFIX.into()),
+    ))
+}
+
+/// Returns every path in `archive_paths` along with the part file path used while downloading it
+pub(crate) fn expected_paths<'a>(
+    archive_paths: impl IntoIterator<Item = &'a Path>,
+) -> HashSet<PathBuf> {
+    archive_paths
+        .into_iter()
+        .flat_map(|path| [path.to_path_buf(), Downloader::get_part_path(path)])
+        .collect()
+}
+
+/// Hardlinks `source_path` to `link_path`, copying it if it can not be hardlinked
+pub(crate) async fn link_file(source_path: &Path, link_path: &Path) -> Result<(), Error> {
+    if let Some(parent) = link_path.parent() {
+        create_dir_all(parent).await?;
+    }
+    if hard_link(source_path, link_path).await.is_err() {
+        copy(source_path, link_path).await?;
+    }
+    Ok(())
+}
+
+/// Returns the path and size of every file in `mirror_path` that is not in `expected_paths`,
+/// sorted by path
+pub(crate) fn find_unexpected_files(
+    mirror_path: &Path,
+    expected_paths: &HashSet<PathBuf>,
+) -> Result<Vec<(PathBuf, u64)>, Error> {
+    let mut unexpected_files = Vec::new();
+    for dir_entry in WalkDir::new(mirror_path) {
+        let dir_entry = dir_entry.map_err(std::io::Error::from)?;
+        if dir_entry.file_type().is_file() && !expected_paths.contains(dir_entry.path()) {
+            let size = dir_entry.metadata().map_err(std::io::Error::from)?.len();
+            unexpected_files.push((dir_entry.into_path(), size));
+        }
+    }
+    unexpected_files.sort();
+    Ok(unexpected_files)
+}
+
+/// Fixtures shared by the tests of mirror operations
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use sha2::Digest;
+    use starview_net::models::AssetPathArchive;
+
+    /// Returns an archive under the mirror's URL prefix at `name` with the size and hash of `contents`
+    pub fn archive(name: &str, contents: impl AsRef<[u8]>) -> AssetPathArchive {
+        let contents = contents.as_ref();
+        AssetPathArchive {
+            location: format!("https://cdn.example.com/patch/gf/upload_assets/{name}"),
+            size: contents.len().try_into().unwrap(),
+            sha256: hex::encode(sha2::Sha256::digest(contents)),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::HashMap,
+    fs::{remove_dir, remove_file},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use tokio::task::spawn_blocking;
+use walkdir::WalkDir;
+
+use crate::{
+    Error,
+    cache::models::FetchCache,
+    mirror::{self, MirrorPaths},
+};
+
+/// A file that is not referenced by any kept asset version
+#[derive(Clone, Debug, Serialize)]
+pub struct PrunedFile {
+    pub path: PathBuf,
+    /// The size of the file in bytes
+    pub size: u64,
+}
+
+/// The result of pruning a mirror with [`prune`]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PruneReport {
+    /// Every file that is not referenced by a kept asset version
+    pub files: Vec<PrunedFile>,
+    /// The combined size of `files` in bytes
+    pub reclaimable_bytes: u64,
+    /// Whether `files` were deleted
+    pub deleted: bool,
+}
+
+/// Finds every file in the mirror at `mirror_path` that is not an archive
+/// of one of `keep_versions`, deleting them if `delete` is true.
+///
+/// The archives of a version are the ones that build it in the mirror,
+/// see [`FetchCache::mirror_archives`].
+///
+/// Part files of kept archives are kept so that their downloads can be resumed,
+/// along with the device indexes of kept versions.
+///
+/// When files are deleted, the hashes of deleted archives are removed from the cache's
+/// downloaded asset hashes, and directories that became empty are removed.
+pub async fn prune(
+    cache: &mut FetchCache,
+    keep_versions: &[&str],
+    mirror_path: impl AsRef<Path>,
+    delete: bool,
+) -> Result<PruneReport, Error> {
+    let mirror_path = mirror_path.as_ref().to_path_buf();
+    let mirror = MirrorPaths::for_cache(cache, &mirror_path);
+
+    let mut kept_archive_paths: Vec<PathBuf> = Vec::new();
+    for keep_version in keep_versions {
+        let cached_version = cache
+            .versions
+            .get(*keep_version)
+            .ok_or_else(|| Error::UnknownAssetVersion(keep_version.to_string()))?;
+        // the same archives that verifying the version expects
+        for archive in cache
+            .mirror_archives(keep_version, None)
+            .unwrap_or_default()
+        {
+            kept_archive_paths.extend(mirror.archive_paths(&archive)?);
+        }
+        kept_archive_paths.extend(mirror.index_paths(keep_version, cached_version));
+    }
+    let expected_paths = mirror::expected_paths(kept_archive_paths.iter().map(PathBuf::as_path));
+
+    let blocking_mirror_path = mirror_path.clone();
+    let report = spawn_blocking(move || {
+        let mirror_path = blocking_mirror_path;
+        let files: Vec<PrunedFile> = mirror::find_unexpected_files(&mirror_path, &expected_paths)?
+            .into_iter()
+            .map(|(path, size)| PrunedFile { path, size })
+            .collect();
+
+        if delete {
+            for file in &files {
+                remove_file(&file.path)?;
+            }
+            remove_empty_dirs(&mirror_path);
+        }
+
+        Ok::<_, Error>(PruneReport {
+            reclaimable_bytes: files.iter().map(|file| file.size).sum(),
+            files,
+            deleted: delete,
+        })
+    })
+    .await??;
+
+    if delete {
+        // archives of other cached versions may have been deleted
+        let mut archive_hashes: HashMap<PathBuf, String> = HashMap::new();
+        for archive in cache
+            .versions
+            .values()
+            .flat_map(|version| version.archives())
+        {
+            for archive_path in mirror.archive_paths(archive)? {
+                archive_hashes.insert(archive_path, archive.sha256.clone());
+            }
+        }
+        for file in &report.files {
+            if let Some(hash) = archive_hashes.get(&file.path) {
+                cache.downloaded_asset_hashes.remove(hash);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Removes every empty directory inside of `dir_path`, ignoring directories that can not be removed
+fn remove_empty_dirs(dir_path: &Path) {
+    for dir_entry in WalkDir::new(dir_path)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .flatten()
+    {
+        if dir_entry.file_type().is_dir() {
+            // fails if the directory is not empty
+            let _ = remove_dir(dir_entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starview_common::enums::DeviceType;
+    use starview_net::models::{AssetPathDiff, AssetPaths, AssetPathsFull, AssetPathsInfo};
+
+    use super::*;
+    use crate::{
+        cache::models::CachedVersion,
+        mirror::{Verifier, test_utils::archive},
+    };
+
+    fn cached_version(version: &str, archive_names: &[&str]) -> CachedVersion {
+        let archive = archive_names
+            .iter()
+            .map(|name| archive(name, name))
+            .collect();
+        CachedVersion::new(
+            DeviceType::All,
+            Vec::new(),
+            AssetPaths {
+                info: AssetPathsInfo {
+                    client_asset_version: version.into(),
+                    target_asset_version: version.into(),
+                    eventual_target_asset_version: version.into(),
+                    is_initial: false,
+                    latest_maj_first_version: version.into(),
+                },
+                full: AssetPathsFull {
+                    version: version.into(),
+                    archive,
+                },
+                diff: Vec::new(),
+                asset_version_hash: String::new(),
+                device_type: None,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("old")).unwrap();
+        std::fs::write(dir.path().join("new.zip"), "n").unwrap();
+        std::fs::write(dir.path().join("new2.zip.part"), "n").unwrap();
+        std::fs::write(dir.path().join("old/old.zip"), "old").unwrap();
+
+        let mut cache = FetchCache::new("udid".into(), DeviceType::All);
+        cache
+            .versions
+            .insert("1".into(), cached_version("1", &["old/old.zip"]));
+        cache
+            .versions
+            .insert("2".into(), cached_version("2", &["new.zip", "new2.zip"]));
+        cache.downloaded_asset_hashes = [
+            archive("old/old.zip", "old/old.zip").sha256,
+            archive("new.zip", "new.zip").sha256,
+        ]
+        .into();
+
+        let report = prune(&mut cache, &["2"], dir.path(), false).await.unwrap();
+        assert_eq!(report.reclaimable_bytes, 3);
+        assert!(dir.path().join("old/old.zip").exists());
+
+        let report = prune(&mut cache, &["2"], dir.path(), true).await.unwrap();
+        assert_eq!(report.files[0].path, dir.path().join("old/old.zip"));
+        assert!(!dir.path().join("old").exists());
+        assert!(dir.path().join("new2.zip.part").exists());
+        assert_eq!(
+            cache.downloaded_asset_hashes,
+            [archive("new.zip", "new.zip").sha256].into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_verified_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["full.zip", "1-2.zip", "2-3.zip", "1-3.zip"] {
+            std::fs::write(dir.path().join(name), name).unwrap();
+        }
+        let diff = |original_version: &str, version: &str| {
+            let name = format!("{original_version}-{version}.zip");
+            AssetPathDiff {
+                version: version.into(),
+                original_version: original_version.into(),
+                archive: vec![archive(&name, &name)],
+            }
+        };
+
+        // 1-3 is larger than 1-2 and 2-3 together, so it is never downloaded
+        let mut cached_version = cached_version("3", &["full.zip"]);
+        cached_version.asset_paths.full.version = "1".into();
+        cached_version.asset_paths.diff = vec![diff("1", "2"), diff("2", "3"), diff("1", "3")];
+        cached_version.asset_paths.diff[2].archive[0].size = 100;
+        let mut cache = FetchCache::new("udid".into(), DeviceType::All);
+        cache.versions.insert("3".into(), cached_version);
+
+        let report = prune(&mut cache, &["3"], dir.path(), true).await.unwrap();
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].path, dir.path().join("1-3.zip"));
+
+        let (verifier, _) = Verifier::for_cache(&cache, Some("3"), dir.path(), 2).unwrap();
+        assert!(verifier.verify().await.unwrap().is_clean());
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use starview_net::models::AssetPathArchive;
+
+use crate::mirror::ArchiveStatus;
+
+/// The state of a [`crate::mirror::Verifier`]
+#[derive(Clone, Debug)]
+pub enum VerifyState {
+    /// Archives with the provided total number of bytes will be verified
+    VerifyStart(u64),
+    /// An archive has been verified
+    ArchiveVerify {
+        archive: AssetPathArchive,
+        status: ArchiveStatus,
+    },
+    /// The mirror is being searched for files that are not in the asset paths
+    FindExtraFiles,
+    /// The mirror has been verified
+    Finish,
+}
+
+/// The state of a [`crate::mirror::Importer`]
+#[derive(Clone, Debug)]
+pub enum ImportState {
+    /// Files with the provided total number of bytes will be hashed
+    ImportStart(u64),
+    /// A file has been hashed
+    FileHash { path: PathBuf, size: u64 },
+    /// A file matched an archive and was imported
+    FileImport {
+        archive: AssetPathArchive,
+        path: PathBuf,
+    },
+    /// A file matched an archive but was not imported,
+    /// because a different file is at `path`
+    FileConflict {
+        archive: AssetPathArchive,
+        path: PathBuf,
+    },
+    /// Every file has been hashed
+    Finish,
+}
@@ -0,0 +1,341 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use futures_util::{StreamExt, stream};
+use serde::Serialize;
+use sha2::Digest;
+use starview_net::models::AssetPathArchive;
+use tokio::{fs::metadata, sync::mpsc, task::spawn_blocking};
+
+use crate::{
+    Error,
+    cache::models::FetchCache,
+    download::Downloader,
+    mirror::{self, MirrorPaths, state::VerifyState},
+};
+
+/// The result of verifying a single archive in a mirror
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum ArchiveStatus {
+    /// The archive exists and matches its size and sha256
+    Valid,
+    /// The archive does not exist
+    Missing,
+    /// The archive exists but does not match its size or sha256
+    Corrupt(String),
+}
+
+/// An archive that did not match its size or sha256
+#[derive(Clone, Debug, Serialize)]
+pub struct CorruptArchive {
+    pub archive: AssetPathArchive,
+    pub reason: String,
+}
+
+/// The result of verifying a mirror with a [`Verifier`]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct VerifyReport {
+    /// The number of archives that matched their size and sha256
+    pub valid: usize,
+    /// Archives that do not exist in the mirror
+    pub missing: Vec<AssetPathArchive>,
+    /// Archives that exist but did not match their size or sha256
+    pub corrupt: Vec<CorruptArchive>,
+    /// Files in the mirror that are not an archive in the asset paths
+    pub extra: Vec<PathBuf>,
+    /// The sha256 of every valid archive
+    #[serde(skip)]
+    pub valid_hashes: HashSet<String>,
+}
+
+impl VerifyReport {
+    /// Returns true if every archive is valid and there are no extra files
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty() && self.extra.is_empty()
+    }
+
+    /// Updates the downloaded asset hashes of `cache` to match this report,
+    /// so that only missing and corrupt archives are downloaded again.
+    pub fn apply_to_cache(&self, cache: &mut FetchCache) {
+        let bad_hashes = self
+            .missing
+            .iter()
+            .chain(self.corrupt.iter().map(|corrupt| &corrupt.archive))
+            .map(|archive| &archive.sha256);
+        for hash in bad_hashes {
+            cache.downloaded_asset_hashes.remove(hash);
+        }
+        cache
+            .downloaded_asset_hashes
+            .extend(self.valid_hashes.iter().cloned());
+    }
+}
+
+/// Interface for checking that the archives in a mirror match their asset paths
+pub struct Verifier {
+    state_sender: mpsc::UnboundedSender<VerifyState>,
+    archives: Vec<AssetPathArchive>,
+    mirror: MirrorPaths,
+    /// Files other than archives that belong in the mirror, such as device indexes
+    index_paths: Vec<PathBuf>,
+    concurrency: usize,
+}
+
+impl Verifier {
+    /// Creates a new Verifier that checks `archives` in `mirror`,
+    /// hashing up to `concurrency` archives at a time.
+    ///
+    /// Every state update is sent to the returned receiver.
+    pub fn new(
+        archives: Vec<AssetPathArchive>,
+        mirror: MirrorPaths,
+        concurrency: usize,
+    ) -> (Self, mpsc::UnboundedReceiver<VerifyState>) {
+        let (state_sender, recv) = mpsc::unbounded_channel();
+
+        (
+            Self {
+                state_sender,
+                archives,
+                mirror,
+                index_paths: Vec::new(),
+                concurrency,
+            },
+            recv,
+        )
+    }
+
+    /// Creates a new Verifier that checks the archives of `asset_version`,
+    /// or of every cached asset version if `asset_version` is None.
+    pub fn for_cache(
+        cache: &FetchCache,
+        asset_version: Option<&str>,
+        mirror_path: impl AsRef<Path>,
+        concurrency: usize,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<VerifyState>), Error> {
+        let versions = match asset_version {
+            Some(asset_version) => vec![(
+                asset_version,
+                cache
+                    .versions
+                    .get(asset_version)
+                    .ok_or_else(|| Error::UnknownAssetVersion(asset_version.into()))?,
+            )],
+            None => cache
+                .versions
+                .iter()
+                .map(|(asset_version, version)| (asset_version.as_str(), version))
+                .collect(),
+        };
+
+        // only the archives that built each version are expected,
+        // diffs that no download applied are never downloaded.
+        // archives are shared between versions, so they are deduplicated by location
+        let archives: HashMap<String, AssetPathArchive> = versions
+            .iter()
+            .flat_map(|(asset_version, _)| {
+                cache
+                    .mirror_archives(asset_version, None)
+                    .unwrap_or_default()
+            })
+            .map(|archive| (archive.location.clone(), archive))
+            .collect();
+        let archives = archives.into_values().collect();
+
+        let mirror = MirrorPaths::for_cache(cache, mirror_path);
+        let index_paths = versions
+            .iter()
+            .flat_map(|(asset_version, version)| mirror.index_paths(asset_version, version))
+            .collect();
+        let (mut verifier, recv) = Self::new(archives, mirror, concurrency);
+        verifier.index_paths = index_paths;
+        Ok((verifier, recv))
+    }
+
+    /// Checks the existence, size, and sha256 of every archive given to this Verifier,
+    /// then searches the mirror for files that are not one of the archives.
+    pub async fn verify(self) -> Result<VerifyReport, Error> {
+        let total_bytes = self.archives.iter().map(|archive| archive.size).sum();
+        let _ = self
+            .state_sender
+            .send(VerifyState::VerifyStart(total_bytes));
+
+        let mut archive_paths: Vec<(AssetPathArchive, Vec<PathBuf>)> =
+            Vec::with_capacity(self.archives.len());
+        for archive in self.archives {
+            let paths = self.mirror.archive_paths(&archive)?;
+            archive_paths.push((archive, paths));
+        }
+        let expected_paths = mirror::expected_paths(
+            archive_paths
+                .iter()
+                .flat_map(|(_, paths)| paths)
+                .chain(&self.index_paths)
+                .map(PathBuf::as_path),
+        );
+
+        let results: Vec<Result<(AssetPathArchive, ArchiveStatus), Error>> =
+            stream::iter(archive_paths)
+                .map(|(archive, paths)| {
+                    let state_sender = self.state_sender.clone();
+                    async move {
+                        // an archive stored at multiple paths is only valid if every copy is
+                        let mut status = ArchiveStatus::Valid;
+                        for path in &paths {
+                            status = verify_archive(&archive, path).await?;
+                            if status != ArchiveStatus::Valid {
+                                break;
+                            }
+                        }
+                        let _ = state_sender.send(VerifyState::ArchiveVerify {
+                            archive: archive.clone(),
+                            status: status.clone(),
+                        });
+                        Ok((archive, status))
+                    }
+                })
+                .buffer_unordered(self.concurrency)
+                .collect()
+                .await;
+
+        let mut report = VerifyReport::default();
+        for result in results {
+            match result? {
+                (archive, ArchiveStatus::Valid) => {
+                    report.valid += 1;
+                    report.valid_hashes.insert(archive.sha256);
+                }
+                (archive, ArchiveStatus::Missing) => report.missing.push(archive),
+                (archive, ArchiveStatus::Corrupt(reason)) => {
+                    report.corrupt.push(CorruptArchive { archive, reason })
+                }
+            }
+        }
+
+        let _ = self.state_sender.send(VerifyState::FindExtraFiles);
+        let mirror_path = self.mirror.mirror_path().to_path_buf();
+        let extra_files =
+            spawn_blocking(move || mirror::find_unexpected_files(&mirror_path, &expected_paths))
+                .await??;
+        report.extra = extra_files.into_iter().map(|(path, _)| path).collect();
+
+        let _ = self.state_sender.send(VerifyState::Finish);
+        Ok(report)
+    }
+}
+
+/// Checks that the archive at `archive_path` matches the size and sha256 of `archive`
+async fn verify_archive(
+    archive: &AssetPathArchive,
+    archive_path: &Path,
+) -> Result<ArchiveStatus, Error> {
+    let archive_metadata = match metadata(archive_path).await {
+        Ok(archive_metadata) => archive_metadata,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(ArchiveStatus::Missing),
+        Err(err) => return Err(err.into()),
+    };
+
+    if archive_metadata.len() != archive.size {
+        return Ok(ArchiveStatus::Corrupt(format!(
+            "expected {} bytes, found {}",
+            archive.size,
+            archive_metadata.len()
+        )));
+    }
+
+    let sha256 = hex::encode(Downloader::hash_file(archive_path).await?.finalize());
+    if !sha256.eq_ignore_ascii_case(&archive.sha256) {
+        return Ok(ArchiveStatus::Corrupt(format!(
+            "expected sha256 {}, found {}",
+            archive.sha256, sha256
+        )));
+    }
+
+    Ok(ArchiveStatus::Valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use starview_common::enums::{DeviceType, MirrorLayout};
+    use starview_net::models::{AssetPathDiff, AssetPaths, AssetPathsFull, AssetPathsInfo};
+
+    use super::*;
+    use crate::{cache::models::CachedVersion, mirror::test_utils::archive};
+
+    #[tokio::test]
+    async fn test_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("valid.zip"), "valid").unwrap();
+        std::fs::write(dir.path().join("corrupt.zip"), "corrupt!").unwrap();
+        std::fs::write(dir.path().join("extra.zip"), "extra").unwrap();
+        std::fs::write(dir.path().join("missing.zip.part"), "mis").unwrap();
+
+        let archives = vec![
+            archive("valid.zip", "valid"),
+            archive("corrupt.zip", "corrupt?"),
+            archive("missing.zip", "missing"),
+        ];
+        let mirror = MirrorPaths::new(dir.path(), MirrorLayout::Merged, DeviceType::All);
+        let (verifier, _) = Verifier::new(archives, mirror, 2);
+        let report = verifier.verify().await.unwrap();
+
+        assert_eq!(report.valid, 1);
+        assert_eq!(
+            report.missing[0].location,
+            archive("missing.zip", "").location
+        );
+        assert!(report.corrupt[0].reason.starts_with("expected sha256"));
+        assert_eq!(report.extra, vec![dir.path().join("extra.zip")]);
+    }
+
+    #[tokio::test]
+    async fn test_for_cache_expects_planned_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let diff = |original_version: &str, version: &str, contents: &str| AssetPathDiff {
+            version: version.into(),
+            original_version: original_version.into(),
+            archive: vec![archive(
+                &format!("{original_version}-{version}.zip"),
+                contents,
+            )],
+        };
+        let asset_paths = AssetPaths {
+            info: AssetPathsInfo {
+                client_asset_version: "3".into(),
+                target_asset_version: "3".into(),
+                eventual_target_asset_version: "3".into(),
+                is_initial: false,
+                latest_maj_first_version: "1".into(),
+            },
+            full: AssetPathsFull {
+                version: "1".into(),
+                archive: vec![archive("full.zip", "full")],
+            },
+            // 1-3 is larger than 1-2 and 2-3 together, so it is not on the planned chain
+            diff: vec![
+                diff("1", "2", "a"),
+                diff("2", "3", "b"),
+                diff("1", "3", "larger"),
+            ],
+            asset_version_hash: String::new(),
+            device_type: None,
+        };
+        for (name, contents) in [("full.zip", "full"), ("1-2.zip", "a"), ("2-3.zip", "b")] {
+            std::fs::write(dir.path().join(name), contents).unwrap();
+        }
+
+        let mut cache = FetchCache::new("udid".into(), DeviceType::All);
+        cache.versions.insert(
+            "3".into(),
+            CachedVersion::new(DeviceType::All, Vec::new(), asset_paths),
+        );
+        let (verifier, _) = Verifier::for_cache(&cache, Some("3"), dir.path(), 2).unwrap();
+        let report = verifier.verify().await.unwrap();
+
+        assert_eq!(report.valid, 3);
+        assert!(report.is_clean());
+    }
+}
@@ -90,6 +90,16 @@ impl WafuriAPIClient {
         Ok(())
     }
 
+    /// Forgets this client's login session,
+    /// so that the next call to [`WafuriAPIClient::signup`] logs in again.
+    pub fn logout(&mut self) {
+        self.headers.remove(header_name::LOGIN_TOKEN);
+        self.headers.remove(header_name::SHORT_UDID);
+        self.login_token = None;
+        self.short_uuid = None;
+        self.viewer_id = None;
+    }
+
     /// Signs up with this client's `uuid`.
     ///
     /// If this client is already logged in, this does nothing.
@@ -166,7 +176,9 @@ impl WafuriAPIClient {
             match request.send().await?.error_for_status() {
                 Ok(response) => {
                     let base64 = response.text().await?;
-                    let load_response: ApiResponse<AssetPaths> = decode_base64_msgpack(&base64)?;
+                    let mut load_response: ApiResponse<AssetPaths> =
+                        decode_base64_msgpack(&base64)?;
+                    load_response.data.device_type = Some(device_type);
                     Ok(Some(load_response.data))
                 }
                 Err(err) => Err(Error::InvalidRequest(err.to_string())),
@@ -180,7 +192,8 @@ impl WafuriAPIClient {
     ///
     /// If the client is not logged in, this will return None
     ///
-    /// If this client's device type was set to be `All`, this performs two requests to get the necessary information
+    /// If this client's device type was set to be `All`, the asset paths of both devices are merged.
+    /// Use [`WafuriAPIClient::get_asset_paths_per_device`] to keep them separate.
     ///
     /// On success, returns the AssetPaths for the provided `target_asset_version` and `asset_size`
     pub async fn get_asset_path(
@@ -188,11 +201,29 @@ impl WafuriAPIClient {
         target_asset_version: &str,
         asset_size: AssetSize,
     ) -> Result<Option<AssetPaths>, Error> {
+        let asset_paths = self
+            .get_asset_paths_per_device(target_asset_version, asset_size)
+            .await?;
+        Ok(asset_paths.into_iter().reduce(AssetPaths::extend))
+    }
+
+    /// Fetches the asset paths of every device from the game server without merging them
+    ///
+    /// If this client's device type was set to be `All`, this performs two requests to get the necessary information
+    ///
+    /// On success, returns the AssetPaths of each device for the provided `target_asset_version` and `asset_size`,
+    /// or an empty Vec if the client is not logged in
+    pub async fn get_asset_paths_per_device(
+        &self,
+        target_asset_version: &str,
+        asset_size: AssetSize,
+    ) -> Result<Vec<AssetPaths>, Error> {
         match self.device_type {
-            DeviceType::Android | DeviceType::Ios => {
-                self.get_asset_path_device_type(target_asset_version, asset_size, self.device_type)
-                    .await
-            }
+            DeviceType::Android | DeviceType::Ios => Ok(self
+                .get_asset_path_device_type(target_asset_version, asset_size, self.device_type)
+                .await?
+                .into_iter()
+                .collect()),
             DeviceType::All => {
                 let android_future = self.get_asset_path_device_type(
                     target_asset_version,
@@ -207,12 +238,7 @@ impl WafuriAPIClient {
 
                 let (android, ios) = try_join!(android_future, ios_future)?;
 
-                Ok(match (android, ios) {
-                    (None, None) => None,
-                    (None, Some(ios)) => Some(ios),
-                    (Some(android), None) => Some(android),
-                    (Some(android), Some(ios)) => Some(android.extend(ios)),
-                })
+                Ok(android.into_iter().chain(ios).collect())
             }
         }
     }
@@ -236,8 +262,9 @@ impl WafuriAPIClient {
             match request.send().await?.error_for_status() {
                 Ok(response) => {
                     let base64 = response.text().await?;
-                    let load_response: ApiResponse<AssetVersionInfo> =
+                    let mut load_response: ApiResponse<AssetVersionInfo> =
                         decode_base64_msgpack(&base64)?;
+                    load_response.data.device_type = Some(device_type);
                     Ok(Some(load_response.data))
                 }
                 Err(err) => Err(Error::InvalidRequest(err.to_string())),
@@ -372,6 +399,12 @@ impl WafuriAPIClientBuilder {
     }
 }
 
+impl Default for WafuriAPIClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl OptionalBuilder for WafuriAPIClientBuilder {}
 
 #[cfg(test)]
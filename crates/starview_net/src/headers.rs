@@ -57,6 +57,11 @@ impl Headers {
         Ok(())
     }
 
+    /// Remove a header if it exists
+    pub fn remove(&mut self, name: &'static str) {
+        self.0.remove(name);
+    }
+
     /// Clones the inner HeaderMap and returns it
     pub fn get_cloned_inner(&self) -> HeaderMap {
         self.0.clone()
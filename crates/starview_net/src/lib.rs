@@ -1,9 +1,9 @@
-mod api_url;
-mod crypto;
 mod error;
-mod headers;
 
+pub mod api_url;
 pub mod client;
+pub mod crypto;
+pub mod headers;
 pub mod models;
 
 pub use error::Error;
@@ -1,8 +1,15 @@
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
+use starview_common::enums::DeviceType;
+use url::Url;
 
-#[derive(Debug, Deserialize)]
+use crate::Error;
+
+/// Result code that the game expects for a successful request
+pub const RESULT_CODE_SUCCESS: u8 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DataHeaders {
     pub short_udid: u32,
     pub viewer_id: u32,
@@ -13,13 +20,13 @@ pub struct DataHeaders {
     pub udid: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub data_headers: DataHeaders,
     pub data: T,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SignupRequest {
     pub oaid: String,
@@ -51,7 +58,7 @@ impl Default for SignupRequest {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SignupResponse {
     #[serde(rename = "login_token")]
@@ -73,7 +80,7 @@ pub struct SignupResponse {
     pub ip: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LoadRequest {
     pub oaid: String,
     pub viewer_id: u32,
@@ -106,12 +113,12 @@ impl LoadRequest {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LoadResponse {
     pub available_asset_version: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GetAssetPathRequest {
     pub target_asset_version: String,
     pub viewer_id: u32,
@@ -161,27 +168,43 @@ pub struct AssetPaths {
     pub full: AssetPathsFull,
     pub diff: Vec<AssetPathDiff>,
     pub asset_version_hash: String,
+    /// The device type that these asset paths were requested for,
+    /// `All` if the asset paths of both devices were merged.
+    ///
+    /// This is not sent by the game server and is set by [`crate::client::WafuriAPIClient`],
+    /// it is not serialized when it is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_type: Option<DeviceType>,
 }
 
 impl AssetPaths {
+    /// Points the location of every archive at the host of `base`,
+    /// see [`rewrite_url_base`]
+    pub fn rewrite_base(&mut self, base: &Url) -> Result<(), Error> {
+        let archives = self
+            .full
+            .archive
+            .iter_mut()
+            .chain(self.diff.iter_mut().flat_map(|diff| &mut diff.archive));
+        for archive in archives {
+            archive.location = rewrite_url_base(&archive.location, base)?;
+        }
+        Ok(())
+    }
+
     /// Merges two AssetPaths together
     ///
     /// Will only merge differences between the two
     pub fn extend(self, with: AssetPaths) -> Self {
         let mut full_archives_map: HashMap<String, AssetPathArchive> = HashMap::new();
-        for archive in self
-            .full
-            .archive
-            .into_iter()
-            .chain(with.full.archive.into_iter())
-        {
+        for archive in self.full.archive.into_iter().chain(with.full.archive) {
             full_archives_map
                 .entry(archive.sha256.clone())
                 .or_insert(archive);
         }
 
         let mut diff_map: HashMap<String, AssetPathsDiffMapEntry> = HashMap::new();
-        for diff in self.diff.into_iter().chain(with.diff.into_iter()) {
+        for diff in self.diff.into_iter().chain(with.diff) {
             let diff_map_entry =
                 diff_map
                     .entry(diff.version.clone())
@@ -205,6 +228,11 @@ impl AssetPaths {
             },
             diff: diff_map.into_values().map(|entry| entry.into()).collect(),
             asset_version_hash: self.asset_version_hash,
+            device_type: if self.device_type == with.device_type {
+                self.device_type
+            } else {
+                Some(DeviceType::All)
+            },
         }
     }
 }
@@ -226,12 +254,12 @@ impl AssetPathsDiffMapEntry {
     }
 }
 
-impl Into<AssetPathDiff> for AssetPathsDiffMapEntry {
-    fn into(self) -> AssetPathDiff {
-        AssetPathDiff {
-            version: self.version,
-            original_version: self.original_version,
-            archive: self.archive_map.into_values().collect(),
+impl From<AssetPathsDiffMapEntry> for AssetPathDiff {
+    fn from(entry: AssetPathsDiffMapEntry) -> Self {
+        Self {
+            version: entry.version,
+            original_version: entry.original_version,
+            archive: entry.archive_map.into_values().collect(),
         }
     }
 }
@@ -257,4 +285,67 @@ pub struct AssetVersionInfo {
     pub files_list: String,
     pub total_size: u64,
     pub delayed_assets_size: u64,
+    /// The device type that this info was requested for.
+    ///
+    /// This is not sent by the game server and is set by [`crate::client::WafuriAPIClient`],
+    /// it is not serialized when it is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_type: Option<DeviceType>,
+}
+
+impl AssetVersionInfo {
+    /// Points `base_url` and `files_list` at the host of `base`,
+    /// see [`rewrite_url_base`]
+    pub fn rewrite_base(&mut self, base: &Url) -> Result<(), Error> {
+        self.base_url = rewrite_url_base(&self.base_url, base)?;
+        self.files_list = rewrite_url_base(&self.files_list, base)?;
+        Ok(())
+    }
+}
+
+/// Moves `url` onto the scheme, host, and port of `base`,
+/// putting the path of `base` in front of its path.
+///
+/// The rest of `url` is kept, so a mirror of the original host can serve it unchanged.
+/// Empty URLs are left empty.
+pub fn rewrite_url_base(url: &str, base: &Url) -> Result<String, Error> {
+    if url.is_empty() {
+        return Ok(String::new());
+    }
+
+    let url = Url::parse(url)?;
+    let mut rewritten = base.clone();
+    rewritten.set_path(&format!(
+        "{}{}",
+        base.path().trim_end_matches('/'),
+        url.path()
+    ));
+    rewritten.set_query(url.query());
+    rewritten.set_fragment(url.fragment());
+    Ok(rewritten.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_url_base() {
+        let url = "https://cdn.example.com/patch/gf/upload_assets/full/a.zip?v=1";
+
+        let base = Url::parse("http://localhost:3001").unwrap();
+        assert_eq!(
+            rewrite_url_base(url, &base).unwrap(),
+            "http://localhost:3001/patch/gf/upload_assets/full/a.zip?v=1"
+        );
+
+        let base = Url::parse("https://mirror.example.com/wf/").unwrap();
+        assert_eq!(
+            rewrite_url_base(url, &base).unwrap(),
+            "https://mirror.example.com/wf/patch/gf/upload_assets/full/a.zip?v=1"
+        );
+
+        assert_eq!(rewrite_url_base("", &base).unwrap(), "");
+        assert!(rewrite_url_base("full/a.zip", &base).is_err());
+    }
 }
@@ -90,7 +90,7 @@ impl FFDec {
 
         let errs: Vec<String> = String::from_utf8_lossy(&import_output.stderr)
             .lines()
-            .filter(|&err| (err.contains("SEVERE") && !err.contains(IGNORE_ERROR)))
+            .filter(|&err| err.contains("SEVERE") && !err.contains(IGNORE_ERROR))
             .map(|err| err.to_string())
             .collect();
 
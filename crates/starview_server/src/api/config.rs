@@ -0,0 +1,59 @@
+use std::net::SocketAddr;
+
+use starview_core::cache::models::FetchCache;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Configuration for [`crate::api::ApiServer`]
+pub struct ApiConfig {
+    /// The address that the server listens on
+    pub address: SocketAddr,
+    /// The cache that asset paths and version info are answered from
+    pub cache: FetchCache,
+    /// The asset version that clients are told to use,
+    /// the cache's default asset version if this is None
+    pub asset_version: Option<String>,
+    /// If set, asset URLs are pointed at this URL so clients download from a mirror
+    pub rewrite_base: Option<Url>,
+    /// If requests with an invalid `param` checksum are rejected
+    pub verify_checksum: bool,
+    /// When cancelled, the server stops accepting connections
+    pub cancellation_token: CancellationToken,
+}
+
+impl ApiConfig {
+    pub fn new(address: SocketAddr, cache: FetchCache) -> Self {
+        Self {
+            address,
+            cache,
+            asset_version: None,
+            rewrite_base: None,
+            verify_checksum: true,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// Sets the asset version that clients are told to use
+    pub fn asset_version(mut self, asset_version: Option<String>) -> Self {
+        self.asset_version = asset_version;
+        self
+    }
+
+    /// Sets the URL that asset URLs are pointed at
+    pub fn rewrite_base(mut self, rewrite_base: Option<Url>) -> Self {
+        self.rewrite_base = rewrite_base;
+        self
+    }
+
+    /// Sets if requests with an invalid `param` checksum are rejected
+    pub fn verify_checksum(mut self, verify_checksum: bool) -> Self {
+        self.verify_checksum = verify_checksum;
+        self
+    }
+
+    /// Sets the token that stops the server when it is cancelled
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+}
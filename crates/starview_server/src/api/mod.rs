@@ -0,0 +1,8 @@
+mod config;
+mod server;
+mod session;
+
+pub mod state;
+
+pub use config::ApiConfig;
+pub use server::ApiServer;
@@ -0,0 +1,390 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use http_body_util::BodyExt;
+use hyper::{HeaderMap, Request, Response, StatusCode, body::Incoming};
+use serde::{Serialize, de::DeserializeOwned};
+use starview_common::enums::DeviceType;
+use starview_core::cache::models::CachedVersion;
+use starview_net::{
+    api_url,
+    crypto::{decode_base64_msgpack, encode_base64_msgpack, get_request_checksum},
+    headers::header_name,
+    models::{
+        ApiResponse, DataHeaders, GetAssetPathRequest, GetAssetVersionInfoRequest, LoadResponse,
+        RESULT_CODE_SUCCESS, SignupResponse,
+    },
+};
+use tokio::{net::TcpListener, sync::mpsc};
+
+use crate::{
+    Error,
+    api::{ApiConfig, session::Session, state::ApiState},
+    http,
+};
+
+/// Prefix of every endpoint on the game server, requests are accepted with or without it
+const API_PATH_PREFIX: &str = "api/index.php/";
+
+/// Local server that answers the game's API requests from a fetch cache
+pub struct ApiServer {
+    state_sender: mpsc::UnboundedSender<ApiState>,
+    config: ApiConfig,
+    /// The asset version that clients are told to use
+    asset_version: String,
+}
+
+impl ApiServer {
+    /// Creates a new ApiServer with the provided config.
+    ///
+    /// Fails if the config does not name an asset version and the cache does not contain one.
+    ///
+    /// Every state update is sent to the returned receiver.
+    pub fn new(config: ApiConfig) -> Result<(Self, mpsc::UnboundedReceiver<ApiState>), Error> {
+        let asset_version = config
+            .asset_version
+            .clone()
+            .or_else(|| config.cache.default_asset_version().map(String::from))
+            .ok_or(starview_core::Error::NoAssetVersion)?;
+        if !config.cache.versions.contains_key(&asset_version) {
+            return Err(Error::UnknownAssetVersion(asset_version));
+        }
+
+        let (state_sender, recv) = mpsc::unbounded_channel();
+        Ok((
+            Self {
+                state_sender,
+                config,
+                asset_version,
+            },
+            recv,
+        ))
+    }
+
+    /// Returns the asset version that clients are told to use
+    pub fn asset_version(&self) -> &str {
+        &self.asset_version
+    }
+
+    /// Answers requests until the config's cancellation token is cancelled
+    pub async fn serve(self) -> Result<(), Error> {
+        let listener = TcpListener::bind(self.config.address).await?;
+        let _ = self
+            .state_sender
+            .send(ApiState::Listening(listener.local_addr()?));
+
+        let cancellation_token = self.config.cancellation_token.clone();
+        let state_sender = self.state_sender.clone();
+        let accept_failed = |err: std::io::Error| {
+            let _ = state_sender.send(ApiState::AcceptFailed(err.to_string()));
+        };
+        let server = Arc::new(self);
+        let result = http::serve(
+            listener,
+            cancellation_token,
+            accept_failed,
+            move |request| {
+                let server = server.clone();
+                async move { server.handle(request).await }
+            },
+        )
+        .await;
+        let _ = state_sender.send(ApiState::Stopped);
+        result
+    }
+
+    /// Answers a single request, reporting it to the state receiver
+    async fn handle(&self, request: Request<Incoming>) -> Response<http::Body> {
+        let path = request.uri().path().to_string();
+        let headers = request.headers().clone();
+        let result = match request.into_body().collect().await {
+            Ok(body) => String::from_utf8(body.to_bytes().to_vec())
+                .map_err(|_| Error::InvalidBody)
+                .and_then(|body| self.respond(&path, &headers, &body)),
+            Err(err) => Err(Error::from(err)),
+        };
+
+        let (response, error) = match result {
+            Ok(body) => (http::text_response(StatusCode::OK, body), None),
+            Err(err) => (
+                http::text_response(err.status(), err.to_string()),
+                Some(err.to_string()),
+            ),
+        };
+        let _ = self.state_sender.send(ApiState::Request {
+            path,
+            status: response.status().as_u16(),
+            error,
+        });
+        response
+    }
+
+    /// Answers a request to `path` with a base64 msgpack body
+    fn respond(&self, path: &str, headers: &HeaderMap, body: &str) -> Result<String, Error> {
+        let endpoint = path.trim_start_matches('/');
+        let endpoint = endpoint.strip_prefix(API_PATH_PREFIX).unwrap_or(endpoint);
+
+        let udid = header(headers, header_name::UDID)?;
+        let session = Session::for_udid(udid);
+
+        // the client signs requests with its viewer ID, which it only knows after signing up
+        if self.config.verify_checksum {
+            let viewer_id = if endpoint == api_url::TOOL_SIGNUP {
+                String::new()
+            } else {
+                session.viewer_id.to_string()
+            };
+            let checksum = get_request_checksum(udid, &viewer_id, path, body);
+            if !header(headers, header_name::PARAM)?.eq_ignore_ascii_case(&checksum) {
+                return Err(Error::InvalidChecksum);
+            }
+        }
+
+        match endpoint {
+            api_url::TOOL_SIGNUP => encode_response(
+                udid,
+                &session,
+                SignupResponse {
+                    login_token: session.login_token.clone(),
+                    new_account: 1,
+                    sign: None,
+                    create_date: None,
+                    role_name: None,
+                    role_id: None,
+                    server_name: None,
+                    server_id: None,
+                    time_used: None,
+                    account_name: None,
+                    login_mode: None,
+                    login_type: None,
+                    credit_account: None,
+                    physical_value: None,
+                    role_level: None,
+                    ip: None,
+                },
+            ),
+            api_url::LOAD => encode_response(
+                udid,
+                &session,
+                LoadResponse {
+                    available_asset_version: self.asset_version.clone(),
+                },
+            ),
+            api_url::ASSET_GET_PATH => {
+                let request: GetAssetPathRequest = decode_request(body)?;
+                let cached_version = self.cached_version(&request.target_asset_version)?;
+                let device_type = self.device_type(headers);
+
+                // prefer the device's own asset paths over the merged ones
+                let mut asset_paths = cached_version
+                    .device_asset_paths
+                    .iter()
+                    .find(|asset_paths| asset_paths.device_type == Some(device_type))
+                    .unwrap_or(&cached_version.asset_paths)
+                    .clone();
+                if let Some(base) = &self.config.rewrite_base {
+                    asset_paths.rewrite_base(base)?;
+                }
+                // the game server does not send the device type
+                asset_paths.device_type = None;
+                encode_response(udid, &session, asset_paths)
+            }
+            api_url::ASSET_VERSION_INFO => {
+                let request: GetAssetVersionInfoRequest = decode_request(body)?;
+                let cached_version = self.cached_version(&request.asset_version)?;
+                let device_type = self.device_type(headers);
+
+                let mut version_info = cached_version
+                    .version_info
+                    .iter()
+                    .find(|info| info.device_type == Some(device_type))
+                    .or(cached_version.version_info.first())
+                    .ok_or_else(|| Error::UnknownAssetVersion(request.asset_version.clone()))?
+                    .clone();
+                if let Some(base) = &self.config.rewrite_base {
+                    version_info.rewrite_base(base)?;
+                }
+                version_info.device_type = None;
+                encode_response(udid, &session, version_info)
+            }
+            _ => Err(Error::UnknownEndpoint(path.into())),
+        }
+    }
+
+    /// Returns the cached asset info of `asset_version`
+    fn cached_version(&self, asset_version: &str) -> Result<&CachedVersion, Error> {
+        self.config
+            .cache
+            .versions
+            .get(asset_version)
+            .ok_or_else(|| Error::UnknownAssetVersion(asset_version.into()))
+    }
+
+    /// Returns the device type that a request was sent from,
+    /// or the cache's device type if the request does not say
+    fn device_type(&self, headers: &HeaderMap) -> DeviceType {
+        headers
+            .get(header_name::DEVICE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(DeviceType::from_code)
+            .unwrap_or(self.config.cache.device_type)
+    }
+}
+
+/// Returns the value of the header `name` in `headers`
+fn header<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<&'a str, Error> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(Error::MissingHeader(name))
+}
+
+/// Decodes a base64 msgpack request body
+fn decode_request<T: DeserializeOwned>(body: &str) -> Result<T, Error> {
+    Ok(decode_base64_msgpack(body)?)
+}
+
+/// Wraps `data` in an [`ApiResponse`] for the client with `udid` and encodes it as base64 msgpack
+fn encode_response<T: Serialize>(udid: &str, session: &Session, data: T) -> Result<String, Error> {
+    let servertime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    Ok(encode_base64_msgpack(&ApiResponse {
+        data_headers: DataHeaders {
+            short_udid: session.short_udid,
+            viewer_id: session.viewer_id,
+            servertime: servertime.try_into().unwrap_or(u32::MAX),
+            result_code: RESULT_CODE_SUCCESS,
+            udid: Some(udid.into()),
+        },
+        data,
+    })?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use hyper::header::HeaderValue;
+    use starview_core::cache::models::FetchCache;
+    use starview_net::models::{
+        AssetPathArchive, AssetPaths, AssetPathsFull, AssetPathsInfo, AssetVersionInfo,
+    };
+
+    use super::*;
+
+    const UDID: &str = "EA5D7426-42A6-474B-26B7-624F5F9B3AF102B3";
+
+    fn asset_paths(device_type: DeviceType, location: &str) -> AssetPaths {
+        AssetPaths {
+            info: AssetPathsInfo {
+                client_asset_version: "1.0.1".into(),
+                target_asset_version: "1.0.1".into(),
+                eventual_target_asset_version: "1.0.1".into(),
+                is_initial: false,
+                latest_maj_first_version: "1.0.0".into(),
+            },
+            full: AssetPathsFull {
+                version: "1.0.0".into(),
+                archive: vec![AssetPathArchive {
+                    location: location.into(),
+                    size: 1,
+                    sha256: location.into(),
+                }],
+            },
+            diff: Vec::new(),
+            asset_version_hash: String::new(),
+            device_type: Some(device_type),
+        }
+    }
+
+    fn server() -> ApiServer {
+        let android = asset_paths(DeviceType::Android, "android.zip");
+        let ios = asset_paths(DeviceType::Ios, "ios.zip");
+        let version_info = AssetVersionInfo {
+            base_url: String::new(),
+            files_list: String::new(),
+            total_size: 0,
+            delayed_assets_size: 0,
+            device_type: Some(DeviceType::Android),
+        };
+
+        let mut cache = FetchCache::new(UDID.into(), DeviceType::All);
+        cache.versions.insert(
+            "1.0.1".into(),
+            CachedVersion::new(
+                DeviceType::All,
+                vec![version_info],
+                android.clone().extend(ios.clone()),
+            )
+            .device_asset_paths(vec![android, ios]),
+        );
+        cache.latest_asset_version = Some("1.0.1".into());
+
+        let address = SocketAddr::from(([127, 0, 0, 1], 0));
+        ApiServer::new(ApiConfig::new(address, cache)).unwrap().0
+    }
+
+    /// Signs a request to `path` the same way the game does
+    fn headers(path: &str, viewer_id: &str, body: &str, device_type: DeviceType) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header_name::UDID, HeaderValue::from_static(UDID));
+        headers.insert(
+            header_name::DEVICE,
+            HeaderValue::from_str(&device_type.to_string()).unwrap(),
+        );
+        let checksum = get_request_checksum(UDID, viewer_id, path, body);
+        headers.insert(
+            header_name::PARAM,
+            HeaderValue::from_str(&checksum).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_signup_and_load() {
+        let server = server();
+
+        let path = "/api/index.php/tool/signup";
+        let response = server
+            .respond(path, &headers(path, "", "A=", DeviceType::Android), "A=")
+            .unwrap();
+        let signup: ApiResponse<SignupResponse> = decode_base64_msgpack(&response).unwrap();
+        let viewer_id = signup.data_headers.viewer_id.to_string();
+        assert_eq!(signup.data.login_token, Session::for_udid(UDID).login_token);
+
+        let path = "/api/index.php/load";
+        let response = server
+            .respond(
+                path,
+                &headers(path, &viewer_id, "B=", DeviceType::Android),
+                "B=",
+            )
+            .unwrap();
+        let load: ApiResponse<LoadResponse> = decode_base64_msgpack(&response).unwrap();
+        assert_eq!(load.data.available_asset_version, "1.0.1");
+
+        // signed without the viewer ID
+        let result = server.respond(path, &headers(path, "", "B=", DeviceType::Android), "B=");
+        assert!(matches!(result, Err(Error::InvalidChecksum)));
+    }
+
+    #[test]
+    fn test_get_path_per_device() {
+        let server = server();
+        let viewer_id = Session::for_udid(UDID).viewer_id;
+        let body =
+            encode_base64_msgpack(&GetAssetPathRequest::new("1.0.1".into(), viewer_id)).unwrap();
+
+        let path = "/api/index.php/asset/get_path";
+        let headers = headers(path, &viewer_id.to_string(), &body, DeviceType::Ios);
+        let response = server.respond(path, &headers, &body).unwrap();
+        let asset_paths: ApiResponse<AssetPaths> = decode_base64_msgpack(&response).unwrap();
+        assert_eq!(asset_paths.data.full.archive[0].location, "ios.zip");
+        assert_eq!(asset_paths.data.device_type, None);
+    }
+}
@@ -0,0 +1,35 @@
+use sha1::{Digest, Sha1};
+
+/// The login session of a client.
+///
+/// Sessions are derived from the client's udid instead of being stored,
+/// so clients stay logged in when the server restarts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Session {
+    pub viewer_id: u32,
+    pub short_udid: u32,
+    pub login_token: String,
+}
+
+impl Session {
+    /// Returns the session of the client with `udid`
+    pub fn for_udid(udid: &str) -> Self {
+        Self {
+            viewer_id: derive_id("viewer_id", udid),
+            short_udid: derive_id("short_udid", udid),
+            login_token: hex_digest("login_token", udid),
+        }
+    }
+}
+
+/// Returns the hex encoded sha1 digest of `salt` followed by `udid`
+fn hex_digest(salt: &str, udid: &str) -> String {
+    hex::encode(Sha1::new().chain_update(salt).chain_update(udid).finalize())
+}
+
+/// Derives a nine digit ID from `udid`, using `salt` to derive different IDs from the same udid
+fn derive_id(salt: &str, udid: &str) -> u32 {
+    let digest = Sha1::new().chain_update(salt).chain_update(udid).finalize();
+    let id = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+    id % 900_000_000 + 100_000_000
+}
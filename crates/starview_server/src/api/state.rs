@@ -0,0 +1,20 @@
+use std::net::SocketAddr;
+
+/// The current state of a [`crate::api::ApiServer`]
+#[derive(Clone, Debug)]
+pub enum ApiState {
+    /// The server is listening on the provided address
+    Listening(SocketAddr),
+    /// A request was answered
+    Request {
+        path: String,
+        /// The HTTP status code of the response
+        status: u16,
+        /// Why the request failed, if it did
+        error: Option<String>,
+    },
+    /// A connection could not be accepted, accepting is retried after a delay
+    AcceptFailed(String),
+    /// The server stopped and every connection was closed
+    Stopped,
+}
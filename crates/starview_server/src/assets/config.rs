@@ -0,0 +1,46 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use starview_core::cache::models::FetchCache;
+use tokio_util::sync::CancellationToken;
+
+/// Configuration for [`crate::assets::AssetServer`]
+pub struct AssetsConfig {
+    /// The address that the server listens on
+    pub address: SocketAddr,
+    /// Path to the mirror that assets are served from
+    pub mirror_path: PathBuf,
+    /// The cache that the mirror was downloaded with, used to find archives in the mirror's layout
+    pub cache: FetchCache,
+    /// Path to the files lists downloaded by `starview fetch list`,
+    /// files lists are looked up in the mirror if this is not set
+    pub lists_path: Option<PathBuf>,
+    /// When cancelled, the server stops accepting connections
+    pub cancellation_token: CancellationToken,
+}
+
+impl AssetsConfig {
+    pub fn new(address: SocketAddr, mirror_path: impl AsRef<Path>, cache: FetchCache) -> Self {
+        Self {
+            address,
+            mirror_path: mirror_path.as_ref().to_path_buf(),
+            cache,
+            lists_path: None,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// Sets the path to the files lists that requests for files lists are answered from
+    pub fn lists_path(mut self, lists_path: Option<PathBuf>) -> Self {
+        self.lists_path = lists_path;
+        self
+    }
+
+    /// Sets the token that stops the server when it is cancelled
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+}
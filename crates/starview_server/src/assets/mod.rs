@@ -0,0 +1,7 @@
+mod config;
+mod server;
+
+pub mod state;
+
+pub use config::AssetsConfig;
+pub use server::AssetServer;
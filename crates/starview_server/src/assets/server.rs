@@ -0,0 +1,489 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use hyper::{
+    HeaderMap, Method, Request, Response, StatusCode,
+    body::Incoming,
+    header::{self, HeaderValue},
+};
+use starview_common::enums::MirrorLayout;
+use starview_core::{
+    cache::models::FetchCache,
+    mirror::{self, ASSET_URL_STRIP_PREFIX, MirrorPaths},
+};
+use starview_net::models::AssetPathArchive;
+use tokio::{fs::File, net::TcpListener, sync::mpsc};
+
+use crate::{
+    Error,
+    assets::{AssetsConfig, state::AssetState},
+    http,
+};
+
+/// Requests for files lists and entities may leave out [`ASSET_URL_STRIP_PREFIX`]
+const ENTITIES_PATH_PREFIX: &str = "/entities/";
+
+/// A file in the mirror that answers a request
+struct MirrorFile<'a> {
+    path: PathBuf,
+    /// The cached archive stored at `path`, if it is one
+    archive: Option<&'a AssetPathArchive>,
+}
+
+/// Local server that answers asset downloads from a mirror, under the same paths as the game's CDN
+pub struct AssetServer {
+    state_sender: mpsc::UnboundedSender<AssetState>,
+    config: AssetsConfig,
+    mirror_paths: MirrorPaths,
+    /// Every cached archive, keyed by its path relative to a merged mirror
+    archives: HashMap<PathBuf, AssetPathArchive>,
+    /// Where every cached files list is in the lists directory,
+    /// keyed by its path relative to the entities directory
+    files_lists: HashMap<PathBuf, PathBuf>,
+}
+
+impl AssetServer {
+    /// Creates a new AssetServer with the provided config.
+    ///
+    /// Every state update is sent to the returned receiver.
+    pub fn new(config: AssetsConfig) -> (Self, mpsc::UnboundedReceiver<AssetState>) {
+        let mirror_paths = MirrorPaths::for_cache(&config.cache, &config.mirror_path);
+        let archives = config
+            .cache
+            .versions
+            .values()
+            .flat_map(|version| version.archives())
+            .filter_map(|archive| {
+                let path = mirror::archive_path(Path::new(""), archive).ok()?;
+                Some((path, archive.clone()))
+            })
+            .collect();
+        let files_lists = match &config.lists_path {
+            Some(lists_path) => files_lists(&config.cache, lists_path),
+            None => HashMap::new(),
+        };
+
+        let (state_sender, recv) = mpsc::unbounded_channel();
+        (
+            Self {
+                state_sender,
+                config,
+                mirror_paths,
+                archives,
+                files_lists,
+            },
+            recv,
+        )
+    }
+
+    /// Answers requests until the config's cancellation token is cancelled
+    pub async fn serve(self) -> Result<(), Error> {
+        let listener = TcpListener::bind(self.config.address).await?;
+        let _ = self
+            .state_sender
+            .send(AssetState::Listening(listener.local_addr()?));
+
+        let cancellation_token = self.config.cancellation_token.clone();
+        let state_sender = self.state_sender.clone();
+        let accept_failed = |err: std::io::Error| {
+            let _ = state_sender.send(AssetState::AcceptFailed(err.to_string()));
+        };
+        let server = Arc::new(self);
+        let result = http::serve(
+            listener,
+            cancellation_token,
+            accept_failed,
+            move |request| {
+                let server = server.clone();
+                async move { server.handle(request).await }
+            },
+        )
+        .await;
+        let _ = state_sender.send(AssetState::Stopped);
+        result
+    }
+
+    /// Answers a single request, reporting it to the state receiver
+    async fn handle(&self, request: Request<Incoming>) -> Response<http::Body> {
+        let path = request.uri().path().to_string();
+        let result = self
+            .respond(request.method(), &path, request.headers())
+            .await;
+
+        let (response, state) = match result {
+            Ok((response, bytes)) => {
+                let status = response.status().as_u16();
+                (
+                    response,
+                    AssetState::Served {
+                        path,
+                        status,
+                        bytes,
+                    },
+                )
+            }
+            Err(err @ Error::UnknownAsset(_)) => (
+                http::text_response(err.status(), err.to_string()),
+                AssetState::Miss(path),
+            ),
+            Err(err) => (
+                http::text_response(err.status(), err.to_string()),
+                AssetState::Failed {
+                    path,
+                    status: err.status().as_u16(),
+                    error: err.to_string(),
+                },
+            ),
+        };
+        let _ = self.state_sender.send(state);
+        response
+    }
+
+    /// Answers a request for the file at `path`,
+    /// returning the response and the number of bytes of the file in it
+    async fn respond(
+        &self,
+        method: &Method,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Result<(Response<http::Body>, u64), Error> {
+        let mirror_file = self.resolve(path)?;
+        let file = File::open(&mirror_file.path).await?;
+        let metadata = file.metadata().await?;
+        let size = metadata.len();
+
+        // archives are tagged by their hash so copies in other mirrors share an etag
+        let etag = match mirror_file.archive {
+            Some(archive) => format!("\"{}\"", archive.sha256.to_lowercase()),
+            None => {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or(0);
+                format!("\"{size:x}-{modified:x}\"")
+            }
+        };
+
+        let mut response = http::text_response(StatusCode::OK, "");
+        let response_headers = response.headers_mut();
+        response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        response_headers.insert(header::ETAG, header_value(&etag)?);
+
+        if header(headers, header::IF_NONE_MATCH)
+            .is_some_and(|if_none_match| etag_matches(if_none_match, &etag))
+        {
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+            return Ok((response, 0));
+        }
+
+        // a stale If-Range means the client's partial copy is outdated, so send the whole file
+        let range = match header(headers, header::RANGE) {
+            Some(_) if header(headers, header::IF_RANGE).is_some_and(|tag| tag != etag) => None,
+            Some(range) => match http::parse_range(range, size) {
+                Ok(range) => range,
+                Err(Error::InvalidRange) => {
+                    *response.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+                    response.headers_mut().insert(
+                        header::CONTENT_RANGE,
+                        header_value(&format!("bytes */{size}"))?,
+                    );
+                    return Ok((response, 0));
+                }
+                Err(err) => return Err(err),
+            },
+            None => None,
+        };
+
+        let bytes = match &range {
+            Some(range) => {
+                *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+                response.headers_mut().insert(
+                    header::CONTENT_RANGE,
+                    header_value(&format!("bytes {}-{}/{}", range.start(), range.end(), size))?,
+                );
+                range.end() + 1 - range.start()
+            }
+            None => size,
+        };
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/octet-stream"),
+        );
+        response
+            .headers_mut()
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(bytes));
+
+        if method == Method::HEAD || bytes == 0 {
+            return Ok((response, 0));
+        }
+        *response.body_mut() = http::file_body(file, range.unwrap_or(0..=size - 1)).await?;
+        Ok((response, bytes))
+    }
+
+    /// Finds the file in the mirror that answers a request for `path`.
+    ///
+    /// Cached archives are found wherever the mirror's layout stores them,
+    /// and cached files lists in the lists directory if it was set.
+    /// Other files are looked up by their path, which only works for layouts that keep paths.
+    fn resolve(&self, path: &str) -> Result<MirrorFile<'_>, Error> {
+        let unknown_asset = || Error::UnknownAsset(path.into());
+        let relative_path = if path.starts_with(ENTITIES_PATH_PREFIX) {
+            path
+        } else {
+            path.strip_prefix(ASSET_URL_STRIP_PREFIX)
+                .filter(|stripped| stripped.starts_with('/'))
+                .ok_or_else(unknown_asset)?
+        };
+        let relative_path = Path::new(relative_path.trim_start_matches('/'));
+
+        // never leave the mirror
+        if !relative_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(unknown_asset());
+        }
+
+        if let Some(archive) = self.archives.get(relative_path) {
+            for archive_path in self.mirror_paths.archive_paths(archive)? {
+                if archive_path.is_file() {
+                    return Ok(MirrorFile {
+                        path: archive_path,
+                        archive: Some(archive),
+                    });
+                }
+            }
+        }
+
+        if let Ok(list_path) = relative_path.strip_prefix(ENTITIES_PATH_PREFIX.trim_matches('/'))
+            && let Some(path) = self.files_lists.get(list_path)
+            && path.is_file()
+        {
+            return Ok(MirrorFile {
+                path: path.clone(),
+                archive: None,
+            });
+        }
+
+        let mirror_path = self.mirror_paths.mirror_path();
+        let search_paths = match self.mirror_paths.layout() {
+            MirrorLayout::Merged => vec![mirror_path.join(relative_path)],
+            MirrorLayout::PerDevice => self
+                .config
+                .cache
+                .device_type
+                .devices()
+                .iter()
+                .map(|device_type| mirror_path.join(device_type.name()).join(relative_path))
+                .collect(),
+            MirrorLayout::ContentAddressed => Vec::new(),
+        };
+        search_paths
+            .into_iter()
+            .find(|search_path| search_path.is_file())
+            .map(|path| MirrorFile {
+                path,
+                archive: None,
+            })
+            .ok_or_else(unknown_asset)
+    }
+}
+
+/// Returns where every files list of the versions in `cache` is saved in `lists_path`,
+/// keyed by its path relative to the entities directory
+fn files_lists(cache: &FetchCache, lists_path: &Path) -> HashMap<PathBuf, PathBuf> {
+    let mut files_lists = HashMap::new();
+    for version in cache.versions.values() {
+        let asset_version = &version.asset_paths.info.client_asset_version;
+        for info in &version.version_info {
+            let (Ok(relative_path), Ok(path)) = (
+                mirror::files_list_path(Path::new(""), "", &info.files_list),
+                mirror::files_list_path(lists_path, asset_version, &info.files_list),
+            ) else {
+                continue;
+            };
+            files_lists.insert(relative_path, path);
+        }
+    }
+    files_lists
+}
+
+/// Returns the value of the header `name` in `headers`, if it is valid text
+fn header(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Creates a header value from text that was built by the server
+fn header_value(value: &str) -> Result<HeaderValue, Error> {
+    HeaderValue::from_str(value).map_err(|_| Error::InvalidHeader(value.into()))
+}
+
+/// Returns true if the value of an If-None-Match header matches `etag`
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use http_body_util::BodyExt;
+    use starview_common::enums::DeviceType;
+    use starview_core::cache::models::CachedVersion;
+    use starview_net::models::{AssetPaths, AssetPathsFull, AssetPathsInfo, AssetVersionInfo};
+
+    use super::*;
+
+    fn cache(layout: MirrorLayout) -> FetchCache {
+        let asset_paths = AssetPaths {
+            info: AssetPathsInfo {
+                client_asset_version: "1.0.1".into(),
+                target_asset_version: "1.0.1".into(),
+                eventual_target_asset_version: "1.0.1".into(),
+                is_initial: false,
+                latest_maj_first_version: "1.0.0".into(),
+            },
+            full: AssetPathsFull {
+                version: "1.0.0".into(),
+                archive: vec![AssetPathArchive {
+                    location: "https://cdn.example.com/patch/gf/upload_assets/full/a.zip".into(),
+                    size: 10,
+                    sha256: "ABCD".into(),
+                }],
+            },
+            diff: Vec::new(),
+            asset_version_hash: String::new(),
+            device_type: Some(DeviceType::Android),
+        };
+
+        let mut cache = FetchCache::new("udid".into(), DeviceType::Android);
+        cache.mirror_layout = layout;
+        cache.versions.insert(
+            "1.0.1".into(),
+            CachedVersion::new(DeviceType::Android, Vec::new(), asset_paths),
+        );
+        cache
+    }
+
+    fn server(mirror_path: &Path, layout: MirrorLayout) -> AssetServer {
+        let address = SocketAddr::from(([127, 0, 0, 1], 0));
+        AssetServer::new(AssetsConfig::new(address, mirror_path, cache(layout))).0
+    }
+
+    #[test]
+    fn test_resolve_layouts() {
+        let dir = tempfile::tempdir().unwrap();
+        let objects_path = dir.path().join("ca/objects/ab");
+        std::fs::create_dir_all(&objects_path).unwrap();
+        std::fs::write(objects_path.join("abcd"), "0123456789").unwrap();
+        let entities_path = dir.path().join("m/entities/android");
+        std::fs::create_dir_all(&entities_path).unwrap();
+        std::fs::write(entities_path.join("list.csv"), "a").unwrap();
+
+        let content_addressed = server(&dir.path().join("ca"), MirrorLayout::ContentAddressed);
+        let mirror_file = content_addressed
+            .resolve("/patch/gf/upload_assets/full/a.zip")
+            .unwrap();
+        assert_eq!(mirror_file.path, objects_path.join("abcd"));
+        assert!(mirror_file.archive.is_some());
+
+        let merged = server(&dir.path().join("m"), MirrorLayout::Merged);
+        for path in [
+            "/entities/android/list.csv",
+            "/patch/gf/upload_assets/entities/android/list.csv",
+        ] {
+            let mirror_file = merged.resolve(path).unwrap();
+            assert_eq!(mirror_file.path, entities_path.join("list.csv"));
+        }
+
+        for path in [
+            "/patch/gf/upload_assets/full/a.zip",
+            "/patch/gf/upload_assets_other/entities/android/list.csv",
+            "/entities/../entities/android/list.csv",
+            "/list.csv",
+        ] {
+            assert!(matches!(merged.resolve(path), Err(Error::UnknownAsset(_))));
+        }
+    }
+
+    #[test]
+    fn test_resolve_fetched_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = cache(MirrorLayout::Merged);
+        let version = cache.versions.get_mut("1.0.1").unwrap();
+        version.version_info.push(AssetVersionInfo {
+            base_url: "https://cdn.example.com/patch/gf/upload_assets/".into(),
+            files_list: "https://cdn.example.com/patch/gf/upload_assets/entities/1a2b/android.csv"
+                .into(),
+            total_size: 0,
+            delayed_assets_size: 0,
+            device_type: Some(DeviceType::Android),
+        });
+
+        // where `starview fetch list` saves the files list
+        let lists_path = dir.path().join("lists");
+        let list_path = lists_path.join("1.0.1/1a2b/android.csv");
+        std::fs::create_dir_all(list_path.parent().unwrap()).unwrap();
+        std::fs::write(&list_path, "a").unwrap();
+
+        let address = SocketAddr::from(([127, 0, 0, 1], 0));
+        let config =
+            AssetsConfig::new(address, dir.path().join("m"), cache).lists_path(Some(lists_path));
+        let (server, _) = AssetServer::new(config);
+        for path in [
+            "/entities/1a2b/android.csv",
+            "/patch/gf/upload_assets/entities/1a2b/android.csv",
+        ] {
+            assert_eq!(server.resolve(path).unwrap().path, list_path);
+        }
+        assert!(matches!(
+            server.resolve("/entities/1a2b/ios.csv"),
+            Err(Error::UnknownAsset(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_respond_range_and_etag() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("full")).unwrap();
+        std::fs::write(dir.path().join("full/a.zip"), "0123456789").unwrap();
+        let server = server(dir.path(), MirrorLayout::Merged);
+        let path = "/patch/gf/upload_assets/full/a.zip";
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_static("bytes=2-4"));
+        let (response, bytes) = server.respond(&Method::GET, path, &headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 2-4/10");
+        assert_eq!(response.headers()[header::ETAG], "\"abcd\"");
+        assert_eq!(bytes, 3);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"234");
+
+        // a stale If-Range sends the whole file
+        headers.insert(header::IF_RANGE, HeaderValue::from_static("\"ef\""));
+        let (response, bytes) = server.respond(&Method::GET, path, &headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(bytes, 10);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"abcd\""));
+        let (response, _) = server.respond(&Method::GET, path, &headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_static("bytes=10-"));
+        let (response, _) = server.respond(&Method::GET, path, &headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */10");
+    }
+}
@@ -0,0 +1,29 @@
+use std::net::SocketAddr;
+
+/// The current state of a [`crate::assets::AssetServer`]
+#[derive(Clone, Debug)]
+pub enum AssetState {
+    /// The server is listening on the provided address
+    Listening(SocketAddr),
+    /// A file from the mirror was sent
+    Served {
+        path: String,
+        /// The HTTP status code of the response
+        status: u16,
+        /// The number of bytes of the file that were sent
+        bytes: u64,
+    },
+    /// A requested file is not in the mirror
+    Miss(String),
+    /// A request failed for a reason other than the file being missing
+    Failed {
+        path: String,
+        /// The HTTP status code of the response
+        status: u16,
+        error: String,
+    },
+    /// A connection could not be accepted, accepting is retried after a delay
+    AcceptFailed(String),
+    /// The server stopped and every connection was closed
+    Stopped,
+}
@@ -0,0 +1,76 @@
+use hyper::StatusCode;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("http server error: {0}")]
+    Hyper(#[from] hyper::Error),
+
+    #[error("upstream request error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("url parse error: {0}")]
+    UrlParse(#[from] url::ParseError),
+
+    #[error("serde JSON error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+
+    #[error("starview network error: {0}")]
+    StarviewNet(#[from] starview_net::Error),
+
+    #[error("core error: {0}")]
+    StarviewCore(#[from] starview_core::Error),
+
+    #[error("recorded body is not valid base64: {0}")]
+    Base64Decode(#[from] base64::DecodeError),
+
+    #[error("request body is not valid utf-8")]
+    InvalidBody,
+
+    #[error("unknown endpoint '{0}'")]
+    UnknownEndpoint(String),
+
+    #[error("request is missing the '{0}' header")]
+    MissingHeader(&'static str),
+
+    #[error("request checksum does not match")]
+    InvalidChecksum,
+
+    #[error("asset version '{0}' is not in the fetch cache")]
+    UnknownAssetVersion(String),
+
+    #[error("'{0}' is not in the mirror")]
+    UnknownAsset(String),
+
+    #[error("'{0}' is not a valid header value")]
+    InvalidHeader(String),
+
+    #[error("requested range is not satisfiable")]
+    InvalidRange,
+
+    #[error("no recorded response for '{0}'")]
+    UnrecordedRequest(String),
+}
+
+impl Error {
+    /// Returns the HTTP status that a request failing with this error is answered with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::InvalidBody
+            | Error::MissingHeader(_)
+            | Error::StarviewNet(_)
+            | Error::UrlParse(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidChecksum => StatusCode::FORBIDDEN,
+            Error::UnknownEndpoint(_)
+            | Error::UnknownAssetVersion(_)
+            | Error::UnknownAsset(_)
+            | Error::UnrecordedRequest(_) => StatusCode::NOT_FOUND,
+            Error::Reqwest(_) => StatusCode::BAD_GATEWAY,
+            Error::InvalidRange => StatusCode::RANGE_NOT_SATISFIABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
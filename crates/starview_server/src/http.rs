@@ -0,0 +1,211 @@
+use std::{convert::Infallible, ops::RangeInclusive, time::Duration};
+
+use futures_util::TryStreamExt;
+use http_body_util::{BodyExt, Full, StreamBody, combinators::UnsyncBoxBody};
+use hyper::{
+    Request, Response, StatusCode,
+    body::{Bytes, Frame, Incoming},
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt, SeekFrom},
+    net::TcpListener,
+};
+use tokio_util::{io::ReaderStream, sync::CancellationToken, task::TaskTracker};
+
+use crate::Error;
+
+/// The body of every response, either held in memory or streamed from a file
+pub(crate) type Body = UnsyncBoxBody<Bytes, std::io::Error>;
+
+/// How long to wait before accepting again after the first failed accept
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// The longest wait before accepting again after consecutive failed accepts
+const MAX_ACCEPT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Accepts connections from `listener` until `cancellation_token` is cancelled,
+/// answering every request on them with `handler`.
+///
+/// Once cancelled, open connections finish the request they are answering and are closed,
+/// and this returns after every connection is closed.
+/// Connection errors only affect the client that caused them, so they are ignored.
+/// Errors when accepting a connection, such as running out of file descriptors,
+/// are passed to `accept_failed` and accepting is retried after a delay.
+pub(crate) async fn serve<F, Fut>(
+    listener: TcpListener,
+    cancellation_token: CancellationToken,
+    accept_failed: impl Fn(std::io::Error) + Send,
+    handler: F,
+) -> Result<(), Error>
+where
+    F: Fn(Request<Incoming>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Response<Body>> + Send + 'static,
+{
+    let connections = TaskTracker::new();
+    let mut retry_delay = ACCEPT_RETRY_DELAY;
+    loop {
+        let accepted = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            accepted = listener.accept() => accepted,
+        };
+        let (stream, _) = match accepted {
+            Ok(accepted) => {
+                retry_delay = ACCEPT_RETRY_DELAY;
+                accepted
+            }
+            Err(err) => {
+                accept_failed(err);
+                // give closing connections time to free what accepting ran out of
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    _ = tokio::time::sleep(retry_delay) => {}
+                }
+                retry_delay = (retry_delay * 2).min(MAX_ACCEPT_RETRY_DELAY);
+                continue;
+            }
+        };
+
+        let handler = handler.clone();
+        let cancellation_token = cancellation_token.clone();
+        connections.spawn(async move {
+            let service = service_fn(move |request| {
+                let handler = handler.clone();
+                async move { Ok::<_, Infallible>(handler(request).await) }
+            });
+            let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+            let mut connection = std::pin::pin!(connection);
+            tokio::select! {
+                _ = connection.as_mut() => {}
+                _ = cancellation_token.cancelled() => {
+                    // keep-alive connections would otherwise stay open after the server stops
+                    connection.as_mut().graceful_shutdown();
+                    let _ = connection.await;
+                }
+            }
+        });
+    }
+
+    connections.close();
+    connections.wait().await;
+    Ok(())
+}
+
+/// Creates a response with `status` and a plain text `body`
+pub(crate) fn text_response(status: StatusCode, body: impl Into<Bytes>) -> Response<Body> {
+    let body = Full::new(body.into())
+        .map_err(|never| match never {})
+        .boxed_unsync();
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    response
+}
+
+/// Creates a body that streams the bytes of `file` in `range`
+pub(crate) async fn file_body(mut file: File, range: RangeInclusive<u64>) -> Result<Body, Error> {
+    file.seek(SeekFrom::Start(*range.start())).await?;
+    let length = range.end() + 1 - range.start();
+    let stream = ReaderStream::new(file.take(length)).map_ok(Frame::data);
+    Ok(StreamBody::new(stream).boxed_unsync())
+}
+
+/// Parses the value of a Range header into the byte range it requests from a file of `size` bytes.
+///
+/// Returns None if the header is not a single byte range, in which case the whole file is sent,
+/// and an error if the range is outside of the file.
+pub(crate) fn parse_range(range: &str, size: u64) -> Result<Option<RangeInclusive<u64>>, Error> {
+    let Some(range) = range.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    // multiple ranges are allowed to be answered with the whole file
+    if range.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = range.split_once('-') else {
+        return Ok(None);
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // a suffix range, the last `end` bytes
+        let suffix: u64 = end.parse().map_err(|_| Error::InvalidRange)?;
+        if suffix == 0 || size == 0 {
+            return Err(Error::InvalidRange);
+        }
+        size.saturating_sub(suffix)..=size - 1
+    } else {
+        let start: u64 = start.parse().map_err(|_| Error::InvalidRange)?;
+        let end = match end {
+            "" => size.saturating_sub(1),
+            end => end
+                .parse::<u64>()
+                .map_err(|_| Error::InvalidRange)?
+                .min(size.saturating_sub(1)),
+        };
+        if start >= size || start > end {
+            return Err(Error::InvalidRange);
+        }
+        start..=end
+    };
+    Ok(Some(range))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-9", 100).unwrap(), Some(0..=9));
+        assert_eq!(parse_range("bytes=90-", 100).unwrap(), Some(90..=99));
+        assert_eq!(parse_range("bytes=-10", 100).unwrap(), Some(90..=99));
+        assert_eq!(parse_range("bytes=50-500", 100).unwrap(), Some(50..=99));
+        assert_eq!(parse_range("bytes=0-1,5-6", 100).unwrap(), None);
+        assert_eq!(parse_range("items=0-1", 100).unwrap(), None);
+
+        assert!(matches!(
+            parse_range("bytes=100-", 100),
+            Err(Error::InvalidRange)
+        ));
+        assert!(matches!(
+            parse_range("bytes=5-1", 100),
+            Err(Error::InvalidRange)
+        ));
+        assert!(matches!(
+            parse_range("bytes=-0", 100),
+            Err(Error::InvalidRange)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_serve_closes_keep_alive_connections() {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let cancellation_token = CancellationToken::new();
+        let server = tokio::spawn(serve(
+            listener,
+            cancellation_token.clone(),
+            |_| {},
+            |_| async { text_response(StatusCode::OK, "ok") },
+        ));
+
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = [0; 1024];
+        let read = stream.read(&mut response).await.unwrap();
+        assert!(response[..read].starts_with(b"HTTP/1.1 200 OK"));
+
+        cancellation_token.cancel();
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), server).await;
+        assert!(result.unwrap().unwrap().is_ok());
+        assert_eq!(stream.read(&mut response).await.unwrap(), 0);
+    }
+}
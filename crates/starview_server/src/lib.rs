@@ -0,0 +1,8 @@
+mod error;
+mod http;
+
+pub mod api;
+pub mod assets;
+pub mod proxy;
+
+pub use error::Error;
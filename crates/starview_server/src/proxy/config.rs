@@ -0,0 +1,43 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// What a [`crate::proxy::ProxyServer`] does with the requests it receives
+#[derive(Clone, Debug)]
+pub enum ProxyMode {
+    /// Forward requests to `upstream`,
+    /// appending every request and response to a new recording file in `recordings_path`
+    Record {
+        upstream: Url,
+        recordings_path: PathBuf,
+    },
+    /// Answer requests with the responses in the recording file,
+    /// or every recording file in the directory, at `recordings_path`
+    Replay { recordings_path: PathBuf },
+}
+
+/// Configuration for [`crate::proxy::ProxyServer`]
+pub struct ProxyConfig {
+    /// The address that the server listens on
+    pub address: SocketAddr,
+    pub mode: ProxyMode,
+    /// When cancelled, the server stops accepting connections
+    pub cancellation_token: CancellationToken,
+}
+
+impl ProxyConfig {
+    pub fn new(address: SocketAddr, mode: ProxyMode) -> Self {
+        Self {
+            address,
+            mode,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// Sets the token that stops the server when it is cancelled
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+}
@@ -0,0 +1,9 @@
+mod config;
+mod recording;
+mod server;
+
+pub mod state;
+
+pub use config::{ProxyConfig, ProxyMode};
+pub use recording::{BodyEncoding, RecordedExchange, Recording};
+pub use server::ProxyServer;
@@ -0,0 +1,273 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use hyper::HeaderMap;
+use serde::{Deserialize, Serialize};
+use starview_common::fs::write_file;
+use starview_net::crypto::decode_base64_msgpack;
+use tokio::{
+    fs::{File, OpenOptions, create_dir_all},
+    io::AsyncWriteExt,
+};
+use url::Url;
+
+use crate::Error;
+
+/// The file extension of recording files
+const RECORDING_EXTENSION: &str = "jsonl";
+
+/// A request that was forwarded upstream and the response that it received
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    pub method: String,
+    /// The path and query of the request's URL
+    pub path: String,
+    pub request_headers: BTreeMap<String, String>,
+    /// The request body as it was sent, stored with `request_body_encoding`
+    pub request_body: String,
+    #[serde(default)]
+    pub request_body_encoding: BodyEncoding,
+    /// The request body decoded from base64 msgpack,
+    /// None if it is not base64 msgpack
+    pub request: Option<serde_json::Value>,
+    /// The HTTP status code of the response
+    pub status: u16,
+    pub response_headers: BTreeMap<String, String>,
+    /// The response body as it was received, stored with `response_body_encoding`
+    pub response_body: String,
+    #[serde(default)]
+    pub response_body_encoding: BodyEncoding,
+    /// The response body decoded from base64 msgpack,
+    /// None if it is not base64 msgpack
+    pub response: Option<serde_json::Value>,
+}
+
+impl RecordedExchange {
+    /// Returns the path of the request's URL without its query
+    pub fn endpoint(&self) -> &str {
+        self.path.split('?').next().unwrap_or_default()
+    }
+}
+
+/// How a recorded body is stored in a string
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyEncoding {
+    /// The body is valid UTF-8 and stored as is
+    #[default]
+    Text,
+    /// The body is not valid UTF-8 and stored as base64
+    Base64,
+}
+
+impl BodyEncoding {
+    /// Returns the bytes of a body that was stored with this encoding
+    pub fn decode(&self, body: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            BodyEncoding::Text => Ok(body.as_bytes().to_vec()),
+            BodyEncoding::Base64 => Ok(BASE64_STANDARD.decode(body)?),
+        }
+    }
+}
+
+/// The first line of a recording file, followed by a line for each exchange
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RecordingHeader {
+    upstream: String,
+    started_at: u64,
+}
+
+/// Every exchange that was recorded during a single run of a [`crate::proxy::ProxyServer`].
+///
+/// Recordings are stored as JSON lines, so exchanges can be appended as they are answered
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recording {
+    /// The URL that requests were forwarded to
+    pub upstream: String,
+    /// Unix timestamp in milliseconds of when recording started
+    pub started_at: u64,
+    /// The recorded exchanges in the order that they were answered
+    pub exchanges: Vec<RecordedExchange>,
+}
+
+impl Recording {
+    pub fn new(upstream: &Url) -> Self {
+        Self {
+            upstream: upstream.to_string(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis().try_into().unwrap_or(u64::MAX))
+                .unwrap_or(0),
+            exchanges: Vec::new(),
+        }
+    }
+
+    /// Loads the recording file at `path`,
+    /// or every recording file in the directory at `path` ordered by file name
+    pub async fn load_all(path: impl AsRef<Path>) -> Result<Vec<Self>, Error> {
+        let path = path.as_ref();
+        let mut recording_paths: Vec<PathBuf> = Vec::new();
+        if path.is_dir() {
+            let mut dir_entries = tokio::fs::read_dir(path).await?;
+            while let Some(dir_entry) = dir_entries.next_entry().await? {
+                let entry_path = dir_entry.path();
+                if entry_path
+                    .extension()
+                    .is_some_and(|ext| ext == RECORDING_EXTENSION)
+                {
+                    recording_paths.push(entry_path);
+                }
+            }
+            recording_paths.sort();
+        } else {
+            recording_paths.push(path.to_path_buf());
+        }
+
+        let mut recordings = Vec::new();
+        for recording_path in recording_paths {
+            let recording = tokio::fs::read_to_string(&recording_path).await?;
+            recordings.push(Self::parse(&recording)?);
+        }
+        Ok(recordings)
+    }
+
+    /// Parses a recording from its JSON lines.
+    ///
+    /// A last line that is not complete, as left by a proxy that was killed while writing it,
+    /// is ignored
+    fn parse(recording: &str) -> Result<Self, Error> {
+        let mut lines = recording.lines().filter(|line| !line.trim().is_empty());
+        let header: RecordingHeader = serde_json::from_str(lines.next().unwrap_or_default())?;
+        let lines: Vec<&str> = lines.collect();
+
+        let mut exchanges = Vec::with_capacity(lines.len());
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(exchange) => exchanges.push(exchange),
+                Err(err) if index + 1 == lines.len() && err.is_eof() => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Self {
+            upstream: header.upstream,
+            started_at: header.started_at,
+            exchanges,
+        })
+    }
+
+    /// Writes this recording as JSON lines to `path`
+    pub async fn write(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut recording = header_line(&self.upstream, self.started_at)?;
+        for exchange in &self.exchanges {
+            recording.extend(exchange_line(exchange)?);
+        }
+        Ok(write_file(&recording, path).await?)
+    }
+}
+
+/// A recording file that exchanges are appended to as they are answered
+pub(crate) struct RecordingFile {
+    file: File,
+}
+
+impl RecordingFile {
+    /// Creates a new recording file for `recording` in the directory at `recordings_path`,
+    /// named after when recording started.
+    ///
+    /// Existing files are never replaced, so proxies that start at the same time
+    /// each get their own file.
+    ///
+    /// Returns the file and its path
+    pub(crate) async fn create(
+        recording: &Recording,
+        recordings_path: &Path,
+    ) -> Result<(Self, PathBuf), Error> {
+        create_dir_all(recordings_path).await?;
+        let mut suffix: usize = 0;
+        let (mut file, path) = loop {
+            let file_name = match suffix {
+                0 => format!("{}.{RECORDING_EXTENSION}", recording.started_at),
+                _ => format!("{}_{suffix}.{RECORDING_EXTENSION}", recording.started_at),
+            };
+            let path = recordings_path.join(file_name);
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+            {
+                Ok(file) => break (file, path),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => suffix += 1,
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        file.write_all(&header_line(&recording.upstream, recording.started_at)?)
+            .await?;
+        file.flush().await?;
+        Ok((Self { file }, path))
+    }
+
+    /// Appends `exchange` to the end of this recording file
+    pub(crate) async fn append(&mut self, exchange: &RecordedExchange) -> Result<(), Error> {
+        self.file.write_all(&exchange_line(exchange)?).await?;
+        Ok(self.file.flush().await?)
+    }
+}
+
+/// Returns the header line of a recording file
+fn header_line(upstream: &str, started_at: u64) -> Result<Vec<u8>, Error> {
+    let mut line = serde_json::to_vec(&RecordingHeader {
+        upstream: upstream.into(),
+        started_at,
+    })?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Returns the line of `exchange` in a recording file
+fn exchange_line(exchange: &RecordedExchange) -> Result<Vec<u8>, Error> {
+    let mut line = serde_json::to_vec(exchange)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Stores `body` as text if it is valid UTF-8, or as base64 otherwise,
+/// along with the body decoded from base64 msgpack if it is base64 msgpack
+pub(crate) fn record_body(body: &[u8]) -> (String, BodyEncoding, Option<serde_json::Value>) {
+    match std::str::from_utf8(body) {
+        Ok(body) => (body.to_string(), BodyEncoding::Text, decode_body(body)),
+        Err(_) => (BASE64_STANDARD.encode(body), BodyEncoding::Base64, None),
+    }
+}
+
+/// Decodes a base64 msgpack body without knowing its type,
+/// returning None if it is empty or not base64 msgpack
+pub(crate) fn decode_body(body: &str) -> Option<serde_json::Value> {
+    let body = body.trim();
+    if body.is_empty() {
+        return None;
+    }
+    decode_base64_msgpack(body).ok()
+}
+
+/// Converts `headers` into a map of names and values,
+/// joining the values of repeated headers
+pub(crate) fn headers_map(headers: &HeaderMap) -> BTreeMap<String, String> {
+    let mut map: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        map.entry(name.to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+    map
+}
@@ -0,0 +1,461 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use http_body_util::BodyExt;
+use hyper::{
+    HeaderMap, Method, Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    header::{self, HeaderName, HeaderValue},
+};
+use tokio::{
+    net::TcpListener,
+    sync::{Mutex, mpsc},
+};
+
+use crate::{
+    Error, http,
+    proxy::{
+        ProxyConfig, ProxyMode, RecordedExchange, Recording,
+        recording::{RecordingFile, headers_map, record_body},
+        state::ProxyState,
+    },
+};
+
+/// Headers that only apply to a single connection, so they are not passed through the proxy
+const HOP_BY_HOP_HEADERS: [HeaderName; 8] = [
+    header::CONNECTION,
+    header::HOST,
+    header::CONTENT_LENGTH,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+];
+
+/// Returns the URL on `upstream` that a request for `path` is forwarded to,
+/// replacing the path and query of `upstream` so a request can not reach another host
+fn upstream_url(upstream: &url::Url, path: &str) -> url::Url {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+    let mut url = upstream.clone();
+    url.set_path(path);
+    url.set_query(query);
+    url
+}
+
+/// The recorded responses of a single endpoint
+struct ReplayQueue {
+    exchanges: Vec<RecordedExchange>,
+    /// The index of the exchange that answers the next request
+    next: usize,
+}
+
+impl ReplayQueue {
+    /// Returns the next recorded exchange,
+    /// repeating the last one once every exchange was replayed
+    fn next(&mut self) -> Option<&RecordedExchange> {
+        let index = self.next.min(self.exchanges.len().checked_sub(1)?);
+        self.next += 1;
+        self.exchanges.get(index)
+    }
+}
+
+enum Mode {
+    Record {
+        client: reqwest::Client,
+        upstream: url::Url,
+        recordings_path: PathBuf,
+        /// Created once the server starts, locked while an exchange is appended
+        /// so the file keeps the order of responses
+        recording_file: Option<Mutex<RecordingFile>>,
+    },
+    Replay {
+        /// Keyed by method and endpoint
+        queues: Mutex<HashMap<(String, String), ReplayQueue>>,
+    },
+}
+
+/// Proxy between the game and its API server that records the traffic,
+/// or replays recorded traffic without the API server
+pub struct ProxyServer {
+    state_sender: mpsc::UnboundedSender<ProxyState>,
+    config: ProxyConfig,
+    mode: Mode,
+}
+
+impl ProxyServer {
+    /// Creates a new ProxyServer with the provided config.
+    ///
+    /// When replaying, every recording is loaded up front.
+    /// When recording, a new recording file is created in the recordings directory once
+    /// the server starts.
+    ///
+    /// Every state update is sent to the returned receiver.
+    pub async fn new(
+        config: ProxyConfig,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<ProxyState>), Error> {
+        let mode = match &config.mode {
+            ProxyMode::Record {
+                upstream,
+                recordings_path,
+            } => Mode::Record {
+                client: reqwest::Client::builder()
+                    .redirect(reqwest::redirect::Policy::none())
+                    .build()?,
+                upstream: upstream.clone(),
+                recordings_path: recordings_path.clone(),
+                recording_file: None,
+            },
+            ProxyMode::Replay { recordings_path } => {
+                let mut queues: HashMap<(String, String), ReplayQueue> = HashMap::new();
+                for recording in Recording::load_all(recordings_path).await? {
+                    for exchange in recording.exchanges {
+                        let key = (exchange.method.clone(), exchange.endpoint().to_string());
+                        queues
+                            .entry(key)
+                            .or_insert_with(|| ReplayQueue {
+                                exchanges: Vec::new(),
+                                next: 0,
+                            })
+                            .exchanges
+                            .push(exchange);
+                    }
+                }
+                Mode::Replay {
+                    queues: Mutex::new(queues),
+                }
+            }
+        };
+
+        let (state_sender, recv) = mpsc::unbounded_channel();
+        Ok((
+            Self {
+                state_sender,
+                config,
+                mode,
+            },
+            recv,
+        ))
+    }
+
+    /// Answers requests until the config's cancellation token is cancelled
+    pub async fn serve(mut self) -> Result<(), Error> {
+        let listener = TcpListener::bind(self.config.address).await?;
+        let _ = self
+            .state_sender
+            .send(ProxyState::Listening(listener.local_addr()?));
+        match &mut self.mode {
+            Mode::Record {
+                upstream,
+                recordings_path,
+                recording_file,
+                ..
+            } => {
+                let (file, recording_path) =
+                    RecordingFile::create(&Recording::new(upstream), recordings_path).await?;
+                *recording_file = Some(Mutex::new(file));
+                let _ = self
+                    .state_sender
+                    .send(ProxyState::Recording(recording_path));
+            }
+            Mode::Replay { queues } => {
+                let exchanges = queues
+                    .lock()
+                    .await
+                    .values()
+                    .map(|queue| queue.exchanges.len())
+                    .sum();
+                let _ = self.state_sender.send(ProxyState::Replaying(exchanges));
+            }
+        }
+
+        let cancellation_token = self.config.cancellation_token.clone();
+        let state_sender = self.state_sender.clone();
+        let accept_failed = |err: std::io::Error| {
+            let _ = state_sender.send(ProxyState::AcceptFailed(err.to_string()));
+        };
+        let server = Arc::new(self);
+        let result = http::serve(
+            listener,
+            cancellation_token,
+            accept_failed,
+            move |request| {
+                let server = server.clone();
+                async move { server.handle(request).await }
+            },
+        )
+        .await;
+        let _ = state_sender.send(ProxyState::Stopped);
+        result
+    }
+
+    /// Answers a single request, reporting it to the state receiver
+    async fn handle(&self, request: Request<Incoming>) -> Response<http::Body> {
+        let method = request.method().clone();
+        let path = request
+            .uri()
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or("/")
+            .to_string();
+        let headers = request.headers().clone();
+
+        let result = match request.into_body().collect().await {
+            Ok(body) => {
+                let body = body.to_bytes();
+                match &self.mode {
+                    Mode::Record { .. } => self.forward(method, &path, &headers, body).await,
+                    Mode::Replay { .. } => self.replay(method, &path).await,
+                }
+            }
+            Err(err) => Err(Error::from(err)),
+        };
+
+        let (response, state) = match result {
+            Ok(response) => response,
+            Err(err) => (
+                http::text_response(err.status(), err.to_string()),
+                ProxyState::Failed {
+                    path,
+                    status: err.status().as_u16(),
+                    error: err.to_string(),
+                },
+            ),
+        };
+        let _ = self.state_sender.send(state);
+        response
+    }
+
+    /// Forwards a request upstream, recording it along with the response
+    async fn forward(
+        &self,
+        method: Method,
+        path: &str,
+        headers: &HeaderMap,
+        body: Bytes,
+    ) -> Result<(Response<http::Body>, ProxyState), Error> {
+        let Mode::Record {
+            client,
+            upstream,
+            recording_file: Some(recording_file),
+            ..
+        } = &self.mode
+        else {
+            unreachable!("requests are only forwarded while recording, once the file was created");
+        };
+
+        let mut upstream_request = client
+            .request(method.clone(), upstream_url(upstream, path))
+            .body(body.clone());
+        for (name, value) in headers {
+            // bodies are recorded as they are received, so they are requested without compression
+            if !HOP_BY_HOP_HEADERS.contains(name) && name != header::ACCEPT_ENCODING {
+                upstream_request = upstream_request.header(name, value);
+            }
+        }
+        let upstream_response = upstream_request.send().await?;
+        let status = upstream_response.status();
+        let response_headers = upstream_response.headers().clone();
+        let response_body = upstream_response.bytes().await?;
+
+        let (request_body, request_body_encoding, request) = record_body(&body);
+        let (recorded_body, response_body_encoding, response) = record_body(&response_body);
+        let exchange = RecordedExchange {
+            method: method.to_string(),
+            path: path.into(),
+            request_headers: headers_map(headers),
+            request_body,
+            request_body_encoding,
+            request,
+            status: status.as_u16(),
+            response_headers: headers_map(&response_headers),
+            response_body: recorded_body,
+            response_body_encoding,
+            response,
+        };
+        recording_file.lock().await.append(&exchange).await?;
+
+        let mut response = http::text_response(status, response_body);
+        for (name, value) in &response_headers {
+            if !HOP_BY_HOP_HEADERS.contains(name) {
+                response.headers_mut().append(name, value.clone());
+            }
+        }
+        let state = ProxyState::Forwarded {
+            path: path.into(),
+            status: status.as_u16(),
+        };
+        Ok((response, state))
+    }
+
+    /// Answers a request with the next recorded response of its endpoint
+    async fn replay(
+        &self,
+        method: Method,
+        path: &str,
+    ) -> Result<(Response<http::Body>, ProxyState), Error> {
+        let Mode::Replay { queues } = &self.mode else {
+            unreachable!("requests are only replayed while replaying");
+        };
+
+        let endpoint = path.split('?').next().unwrap_or_default();
+        let mut queues = queues.lock().await;
+        let exchange = queues
+            .get_mut(&(method.to_string(), endpoint.to_string()))
+            .and_then(ReplayQueue::next)
+            .ok_or_else(|| Error::UnrecordedRequest(path.into()))?;
+
+        let status =
+            StatusCode::from_u16(exchange.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let response_body = exchange
+            .response_body_encoding
+            .decode(&exchange.response_body)?;
+        let mut response = http::text_response(status, response_body);
+        for (name, value) in &exchange.response_headers {
+            let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) else {
+                continue;
+            };
+            if !HOP_BY_HOP_HEADERS.contains(&name) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        let state = ProxyState::Replayed {
+            path: path.into(),
+            status: status.as_u16(),
+        };
+        Ok((response, state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, net::SocketAddr};
+
+    use url::Url;
+
+    use super::*;
+    use crate::proxy::{BodyEncoding, recording::decode_body};
+
+    fn exchange(path: &str, response_body: &str) -> RecordedExchange {
+        RecordedExchange {
+            method: "POST".into(),
+            path: path.into(),
+            request_headers: BTreeMap::new(),
+            request_body: String::new(),
+            request_body_encoding: BodyEncoding::Text,
+            request: None,
+            status: 200,
+            response_headers: BTreeMap::from([("x-test".to_string(), response_body.to_string())]),
+            response_body: response_body.into(),
+            response_body_encoding: BodyEncoding::Text,
+            response: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recording = Recording::new(&Url::parse("https://example.com").unwrap());
+        recording.exchanges = vec![
+            exchange("/api/index.php/load?a=1", "first"),
+            exchange("/api/index.php/tool/signup", "signup"),
+            exchange("/api/index.php/load", "second"),
+        ];
+        recording.write(dir.path().join("1.jsonl")).await.unwrap();
+
+        let address = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mode = ProxyMode::Replay {
+            recordings_path: dir.path().to_path_buf(),
+        };
+        let (server, _) = ProxyServer::new(ProxyConfig::new(address, mode))
+            .await
+            .unwrap();
+
+        for expected in ["first", "second", "second"] {
+            let (response, _) = server
+                .replay(Method::POST, "/api/index.php/load")
+                .await
+                .unwrap();
+            assert_eq!(response.headers()["x-test"], expected);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(&body[..], expected.as_bytes());
+        }
+
+        let result = server.replay(Method::GET, "/api/index.php/load").await;
+        assert!(matches!(result, Err(Error::UnrecordedRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_recording_file_appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let recording = Recording::new(&Url::parse("https://example.com").unwrap());
+        let (mut first, first_path) = RecordingFile::create(&recording, dir.path()).await.unwrap();
+        // a proxy started in the same millisecond gets its own file
+        let (mut second, second_path) =
+            RecordingFile::create(&recording, dir.path()).await.unwrap();
+        assert_ne!(first_path, second_path);
+
+        first.append(&exchange("/a", "a")).await.unwrap();
+        second.append(&exchange("/b", "b")).await.unwrap();
+        first.append(&exchange("/c", "c")).await.unwrap();
+
+        // a line that was cut off while it was written is ignored
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&second_path)
+            .unwrap();
+        std::io::Write::write_all(&mut file, b"{\"method\":").unwrap();
+
+        let recordings = Recording::load_all(dir.path()).await.unwrap();
+        let paths: Vec<Vec<&str>> = recordings
+            .iter()
+            .map(|recording| {
+                recording
+                    .exchanges
+                    .iter()
+                    .map(|exchange| exchange.path.as_str())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(paths, vec![vec!["/a", "/c"], vec!["/b"]]);
+    }
+
+    #[test]
+    fn test_decode_body() {
+        let body =
+            starview_net::crypto::encode_base64_msgpack(&BTreeMap::from([("a", 1)])).unwrap();
+        assert_eq!(decode_body(&body), Some(serde_json::json!({ "a": 1 })));
+        assert_eq!(decode_body(""), None);
+        assert_eq!(decode_body("<html>"), None);
+    }
+
+    #[test]
+    fn test_record_body() {
+        let (body, encoding, _) = record_body(b"text");
+        assert_eq!((body.as_str(), encoding), ("text", BodyEncoding::Text));
+
+        let binary = [0x1f, 0x8b, 0xff, 0x00];
+        let (body, encoding, decoded) = record_body(&binary);
+        assert_eq!(encoding, BodyEncoding::Base64);
+        assert_eq!(decoded, None);
+        assert_eq!(encoding.decode(&body).unwrap(), binary);
+    }
+
+    #[test]
+    fn test_upstream_url() {
+        let upstream = Url::parse("https://example.com/").unwrap();
+        assert_eq!(
+            upstream_url(&upstream, "/api/index.php/load?a=1").as_str(),
+            "https://example.com/api/index.php/load?a=1"
+        );
+        assert_eq!(
+            upstream_url(&upstream, "//other.example.com/x").host_str(),
+            Some("example.com")
+        );
+    }
+}
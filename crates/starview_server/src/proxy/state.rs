@@ -0,0 +1,35 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+/// The current state of a [`crate::proxy::ProxyServer`]
+#[derive(Clone, Debug)]
+pub enum ProxyState {
+    /// The server is listening on the provided address
+    Listening(SocketAddr),
+    /// Requests are being recorded to the provided file
+    Recording(PathBuf),
+    /// The provided number of recorded exchanges were loaded for replaying
+    Replaying(usize),
+    /// A request was forwarded upstream and recorded
+    Forwarded {
+        path: String,
+        /// The HTTP status code of the upstream response
+        status: u16,
+    },
+    /// A request was answered from a recording
+    Replayed {
+        path: String,
+        /// The HTTP status code of the recorded response
+        status: u16,
+    },
+    /// A request could not be answered
+    Failed {
+        path: String,
+        /// The HTTP status code of the response
+        status: u16,
+        error: String,
+    },
+    /// A connection could not be accepted, accepting is retried after a delay
+    AcceptFailed(String),
+    /// The server stopped and every connection was closed
+    Stopped,
+}